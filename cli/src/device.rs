@@ -0,0 +1,25 @@
+/* Connecting to a narvie processor on an FPGA, which needs the serial port
+ * support that the other ways of connecting to narvie do not.
+ */
+
+use narvie_isa::device::StreamDevice;
+use std::time::Duration;
+
+/* A narvie processor on an FPGA, connected by a serial port.
+ */
+pub type SerialDevice = StreamDevice<Box<dyn serialport::SerialPort>>;
+
+pub fn serial(address: &str, baud_rate: u32) -> Result<SerialDevice, serialport::Error> {
+    serialport::open_with_settings(
+        address,
+        &serialport::SerialPortSettings {
+            baud_rate,
+            data_bits: serialport::DataBits::Eight,
+            flow_control: serialport::FlowControl::None,
+            parity: serialport::Parity::None,
+            stop_bits: serialport::StopBits::One,
+            timeout: Duration::from_millis(500),
+        },
+    )
+    .map(StreamDevice::new)
+}
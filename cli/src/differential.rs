@@ -0,0 +1,203 @@
+/* Runs the same instructions through two narvie backends, for example the
+ * Verilator simulation and the software model, to check that they agree.
+ */
+
+#[cfg(test)]
+use narvie_isa::device::MockDevice;
+use narvie_isa::device::{DeviceError, NarvieDevice};
+use narvie_isa::instruction::Instruction;
+use narvie_isa::register_file::RegisterFile;
+#[cfg(test)]
+use narvie_processor::model::Model;
+use std::fmt;
+
+/* The first instruction after which the register files returned by the two
+ * backends differ.
+ */
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub instruction: u32,
+    pub left: RegisterFile,
+    pub right: RegisterFile,
+}
+
+/* Instructions are shown as mnemonics when they can be decoded.
+ */
+pub fn describe(word: u32) -> String {
+    match Instruction::from_u32(word) {
+        Ok(instruction) => format!("0x{:08X}  {}", word, instruction),
+        Err(_) => format!("0x{:08X}", word),
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Register files differ after instruction {}: {}",
+            self.index + 1,
+            describe(self.instruction)
+        )?;
+        for change in self.left.diff(&self.right) {
+            writeln!(
+                f,
+                "  {:>3} ({:>4})  0x{:08X}  0x{:08X}",
+                change.register.to_string(),
+                change.register.abi_name(),
+                change.old,
+                change.new
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn first_divergence<L, R>(
+    instructions: &[u32],
+    left: &mut L,
+    right: &mut R,
+) -> Result<Option<Divergence>, DeviceError>
+where
+    L: NarvieDevice,
+    R: NarvieDevice,
+{
+    for (index, instruction) in instructions.iter().enumerate() {
+        let left = left.execute(*instruction)?;
+        let right = right.execute(*instruction)?;
+        if left != right {
+            return Ok(Some(Divergence {
+                index,
+                instruction: *instruction,
+                left,
+                right,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/* Removes instructions, first in large chunks and then one at a time, for
+ * as long as the backends still disagree. `backends` is called to start a
+ * fresh pair of backends for each attempt, as the state left by earlier
+ * instructions would otherwise change the result.
+ */
+pub fn minimize<F, L, R, E>(instructions: &[u32], mut backends: F) -> Result<Vec<u32>, E>
+where
+    F: FnMut() -> Result<(L, R), E>,
+    L: NarvieDevice,
+    R: NarvieDevice,
+    E: From<DeviceError>,
+{
+    let mut diverges = |instructions: &[u32]| -> Result<Option<usize>, E> {
+        let (mut left, mut right) = backends()?;
+        let divergence = first_divergence(instructions, &mut left, &mut right)?;
+        Ok(divergence.map(|divergence| divergence.index))
+    };
+
+    let mut current = match diverges(instructions)? {
+        Some(index) => instructions[..=index].to_vec(),
+        None => return Ok(instructions.to_vec()),
+    };
+
+    let mut chunk = current.len() / 2;
+    while chunk > 0 {
+        let mut start = 0;
+        while start < current.len() {
+            let end = (start + chunk).min(current.len());
+            let candidate: Vec<u32> = current[..start]
+                .iter()
+                .chain(&current[end..])
+                .cloned()
+                .collect();
+
+            match diverges(&candidate)? {
+                Some(index) if !candidate.is_empty() => {
+                    current = candidate[..=index].to_vec();
+                }
+                _ => start += chunk,
+            }
+        }
+        chunk /= 2;
+    }
+
+    Ok(current)
+}
+
+/* The software model with `sub` broken so that it adds, for testing code
+ * that compares backends.
+ */
+#[cfg(test)]
+pub fn sub_adds() -> MockDevice<impl FnMut(u32) -> RegisterFile> {
+    let mut model = Model::new();
+    MockDevice::new(move |instruction| {
+        if instruction & 0xFE00_707F == 0x4000_0033 {
+            model.execute(instruction & !0x4000_0000);
+        } else {
+            model.execute(instruction);
+        }
+        RegisterFile::new(model.registers())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &[u32] = &[
+        0x0050_0513, // addi a0, zero, 5
+        0x0030_0593, // addi a1, zero, 3
+        0x0010_0613, // addi a2, zero, 1
+        0x40B5_06B3, // sub a3, a0, a1
+        0x0020_0713, // addi a4, zero, 2
+        0x40B5_07B3, // sub a5, a0, a1
+    ];
+
+    #[test]
+    fn finds_first_divergence() {
+        let divergence = first_divergence(PROGRAM, &mut Model::new(), &mut sub_adds())
+            .unwrap()
+            .unwrap();
+        assert_eq!(divergence.index, 3);
+        assert_eq!(divergence.left["a3"], 2);
+        assert_eq!(divergence.right["a3"], 8);
+
+        assert_eq!(
+            first_divergence(PROGRAM, &mut sub_adds(), &mut sub_adds()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn minimizes_reproducer() {
+        let reproducer = minimize(PROGRAM, || -> Result<_, DeviceError> {
+            Ok((Model::new(), sub_adds()))
+        })
+        .unwrap();
+        assert_eq!(reproducer, vec![0x0030_0593, 0x40B5_06B3]);
+    }
+
+    #[cfg(feature = "verilator")]
+    #[test]
+    fn verilator_memory_matches_model() {
+        let program = [
+            0xFFF0_0513, // addi a0, zero, -1
+            0x00A0_2223, // sw a0, 4(zero)
+            0x1230_0593, // addi a1, zero, 0x123
+            0x00B0_02A3, // sb a1, 5(zero)
+            0x00A0_1323, // sh a0, 6(zero)
+            0x0040_0603, // lb a2, 4(zero)
+            0x0050_1683, // lh a3, 5(zero)
+            0x0060_4703, // lbu a4, 6(zero)
+            0x0070_5783, // lhu a5, 7(zero)
+            0x7FC0_2803, // lw a6, 2044(zero)
+            0x7EB0_2E23, // sw a1, 2044(zero)
+            0x7FC0_2883, // lw a7, 2044(zero)
+        ];
+        let mut verilator = narvie_processor::simulation::Simulation::start();
+        assert_eq!(
+            first_divergence(&program, &mut verilator, &mut Model::new()).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use narvie_isa::compressed::{self, CompressedInstruction};
+use narvie_isa::diagnostic::Span;
+use narvie_isa::immediate::{self, Constraints, Immediate};
+use narvie_isa::instruction::{self, Error};
+use narvie_isa::pseudo;
+use narvie_isa::register::{self, Register};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/* ANSI escape codes used to colour the input line.
+ */
+const MNEMONIC: &str = "\x1b[1;34m";
+const REGISTER: &str = "\x1b[32m";
+const IMMEDIATE: &str = "\x1b[33m";
+const MEMORY: &str = "\x1b[36m";
+const INVALID: &str = "\x1b[1;31m";
+const HINT: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/* Completes mnemonics, registers and CSR names at the narvie prompt and hints
+ * at the operands that the instruction being typed still needs.
+ */
+pub struct NarvieHelper {
+    mnemonics: Vec<&'static str>,
+    meta_commands: Vec<&'static str>,
+    files: FilenameCompleter,
+    registers: Vec<String>,
+    csrs: Vec<&'static str>,
+}
+
+impl NarvieHelper {
+    pub fn new() -> Self {
+        let mut mnemonics: Vec<&'static str> = instruction::mnemonics()
+            .chain(pseudo::MNEMONICS.iter().cloned())
+            .chain(compressed::MNEMONICS.iter().cloned())
+            .collect();
+        mnemonics.sort();
+        mnemonics.dedup();
+
+        let csrs = immediate::CSR_NAMES.iter().map(|(name, _)| *name).collect();
+
+        NarvieHelper {
+            mnemonics,
+            meta_commands: crate::META_COMMANDS
+                .iter()
+                .map(|(name, _, _)| *name)
+                .collect(),
+            files: FilenameCompleter::new(),
+            registers: register::register_names(),
+            csrs,
+        }
+    }
+}
+
+/* Where the name of a meta-command such as `:help` starts and ends in `line`.
+ */
+fn meta_command_span(line: &str) -> Option<(Span, &str)> {
+    let trimmed = line.trim_start();
+    let (name, _) = crate::meta_command(trimmed)?;
+    let start = line.len() - trimmed.len();
+    Some((Span::new(start, start + 1 + name.len()), name))
+}
+
+fn pairs<'a, I>(candidates: I, word: &str) -> Vec<Pair>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(word))
+        .map(|candidate| Pair {
+            display: candidate.to_string(),
+            replacement: candidate.to_string(),
+        })
+        .collect()
+}
+
+impl NarvieHelper {
+    fn complete_meta_command(
+        &self,
+        line: &str,
+        pos: usize,
+        span: Span,
+        name: &str,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        if pos <= span.end {
+            let prefix = &line[span.start..span.start + 1];
+            let commands: Vec<String> = self
+                .meta_commands
+                .iter()
+                .map(|command| format!("{}{}", prefix, command))
+                .collect();
+            Ok((
+                span.start,
+                pairs(commands.iter().map(String::as_str), &line[span.start..pos]),
+            ))
+        } else {
+            match name {
+                "help" => {
+                    let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+                    Ok((
+                        start,
+                        pairs(self.mnemonics.iter().cloned(), &line[start..pos]),
+                    ))
+                }
+                "load" | "save-history" => self.files.complete_path(line, pos),
+                _ => Ok((pos, vec![])),
+            }
+        }
+    }
+}
+
+impl Completer for NarvieHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        if let Some((span, name)) = meta_command_span(line) {
+            return self.complete_meta_command(line, pos, span, name);
+        }
+
+        let start = line[..pos].rfind(&[' ', ',', '('][..]).map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+
+        let candidates: Vec<&str> = if line[..start].trim().is_empty() {
+            self.mnemonics.clone()
+        } else {
+            let name = line.trim_start().split(' ').next().unwrap_or("");
+            let csrs = if name.to_ascii_lowercase().starts_with("csr") {
+                &self.csrs[..]
+            } else {
+                &[]
+            };
+            self.registers
+                .iter()
+                .map(String::as_str)
+                .chain(csrs.iter().cloned())
+                .collect()
+        };
+
+        Ok((start, pairs(candidates, word)))
+    }
+}
+
+impl Hinter for NarvieHelper {
+    fn hint(&self, line: &str, pos: usize, _: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+
+        if let Some((span, name)) = meta_command_span(line) {
+            let (_, arguments, _) = crate::META_COMMANDS.iter().find(|(n, _, _)| *n == name)?;
+            return if line[span.end..] == *" " && !arguments.is_empty() {
+                Some(arguments.to_string())
+            } else {
+                None
+            };
+        }
+
+        let line = line.trim_start();
+        let space = line.find(' ')?;
+        let (name, rest) = line.split_at(space);
+
+        let operands: Vec<&str> = instruction::metadata(name)?
+            .format
+            .operands()
+            .split(", ")
+            .filter(|operand| !operand.is_empty())
+            .collect();
+
+        let typed: Vec<&str> = rest.split(',').collect();
+        let index = typed.len() - 1;
+        if index >= operands.len() {
+            return None;
+        }
+
+        if typed[index].trim().is_empty() {
+            /* About to start an operand. */
+            let separator = if typed[index].is_empty() { " " } else { "" };
+            Some(format!("{}{}", separator, operands[index..].join(", ")))
+        } else if index + 1 < operands.len() {
+            /* Part way through an operand. */
+            Some(format!(", {}", operands[index + 1..].join(", ")))
+        } else {
+            None
+        }
+    }
+}
+
+fn is_register(operand: &str) -> bool {
+    Register::<()>::from_str(operand).is_ok()
+}
+
+fn is_immediate(operand: &str) -> bool {
+    Immediate::<immediate::Word>::from_str(operand).is_ok()
+        || immediate::CsrSpecifier::from_special_string(operand).is_some()
+}
+
+fn is_memory_location(operand: &str) -> bool {
+    match (operand.find('('), operand.strip_suffix(')')) {
+        (Some(open), Some(inside)) => {
+            (open == 0 || is_immediate(&operand[..open])) && is_register(&inside[open + 1..])
+        }
+        _ => false,
+    }
+}
+
+fn is_fence_ordering(operand: &str) -> bool {
+    !operand.is_empty() && operand.chars().all(|c| "iorw".contains(c))
+}
+
+impl NarvieHelper {
+    /* The colour of each byte of `line`, or `None` for separators. */
+    fn colours(&self, line: &str) -> Vec<Option<&'static str>> {
+        let mut colours = vec![None; line.len()];
+        let mut paint = |span: Span, colour| {
+            for c in &mut colours[span.start..span.end] {
+                *c = Some(colour);
+            }
+        };
+
+        if let Some((span, name)) = meta_command_span(line) {
+            let known = self.meta_commands.contains(&name);
+            paint(span, if known { MNEMONIC } else { INVALID });
+            return colours;
+        }
+
+        let (name_span, args) = instruction::split_mnemonic_spans(line);
+        let name = &line[name_span.start..name_span.end];
+
+        if args.is_empty() && crate::parse_word(name).is_some() {
+            paint(name_span, IMMEDIATE);
+            return colours;
+        }
+
+        let is_mnemonic = self.mnemonics.iter().any(|m| m.eq_ignore_ascii_case(name));
+        paint(name_span, if is_mnemonic { MNEMONIC } else { INVALID });
+
+        for span in args {
+            let operand = &line[span.start..span.end];
+            let colour = if is_register(operand) {
+                REGISTER
+            } else if is_immediate(operand) {
+                IMMEDIATE
+            } else if is_memory_location(operand) {
+                MEMORY
+            } else if name.eq_ignore_ascii_case("fence") && is_fence_ordering(operand) {
+                IMMEDIATE
+            } else {
+                INVALID
+            };
+            paint(span, colour);
+        }
+
+        /* Operands can be well formed but still not fit the instruction, for
+         * example an immediate that is out of range. Missing operands are
+         * not marked as the user may not have typed them yet.
+         */
+        if is_mnemonic {
+            let error = if crate::is_compressed(name) {
+                CompressedInstruction::from_str(line).err()
+            } else {
+                pseudo::parse(line).err()
+            };
+            if let Some(error) = error {
+                let span = error.span(line);
+                match error {
+                    Error::WrongNumberOfArgs { .. } if span.start == span.end => {}
+                    _ => paint(span, INVALID),
+                }
+            }
+        }
+
+        colours
+    }
+}
+
+impl Highlighter for NarvieHelper {
+    fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
+        let colours = self.colours(line);
+        if colours.iter().all(Option::is_none) {
+            return Cow::Borrowed(line);
+        }
+
+        let mut highlighted = String::with_capacity(line.len() * 2);
+        let mut current = None;
+        for (i, c) in line.char_indices() {
+            if colours[i] != current {
+                if current.is_some() {
+                    highlighted.push_str(RESET);
+                }
+                if let Some(colour) = colours[i] {
+                    highlighted.push_str(colour);
+                }
+                current = colours[i];
+            }
+            highlighted.push(c);
+        }
+        if current.is_some() {
+            highlighted.push_str(RESET);
+        }
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("{}{}{}", HINT, hint, RESET))
+    }
+
+    fn highlight_char(&self, _: &str, _: usize) -> bool {
+        true
+    }
+}
+
+impl Helper for NarvieHelper {}
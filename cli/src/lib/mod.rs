@@ -1,3 +0,0 @@
-pub mod immediate;
-pub mod instruction;
-pub mod register;
@@ -1,3 +1,4 @@
+extern crate atty;
 extern crate clap;
 extern crate directories;
 extern crate env_logger;
@@ -8,37 +9,52 @@ extern crate rustyline;
 extern crate serialport;
 extern crate time;
 
-mod lib;
+mod device;
+mod differential;
+mod helper;
 
 use clap::{App, Arg};
 use directories::ProjectDirs;
-use lib::instruction::{self, Instruction};
-use lib::register::{self, Register};
+use helper::NarvieHelper;
 use log::{debug, error, info, warn};
+use narvie_isa::compressed::{self, CompressedInstruction};
+use narvie_isa::device::{DeviceError, NarvieDevice};
+use narvie_isa::instruction::{self, Instruction};
+use narvie_isa::pseudo;
+use narvie_isa::random;
+use narvie_isa::register::{self, Register};
+use narvie_isa::register_file::{NumericView, RegisterFile, NUMERIC_VIEWS};
+use narvie_processor::model::Model;
+#[cfg(feature = "verilator")]
+use narvie_processor::simulation::Simulation;
 use prettytable::*;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 use std::error::Error;
 use std::fmt::{self, Debug, Display};
 use std::fs::{self, File};
-use std::io;
-use std::net::TcpStream;
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::thread;
-use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug)]
 enum EvalInstructionError {
     Parse(instruction::Error),
+    Decode(instruction::DecodeError),
     Write(io::Error),
     Read(io::Error),
 }
 
-trait ReadWrite: io::Read + io::Write {}
-impl<T: io::Read + io::Write> ReadWrite for T {}
+impl From<DeviceError> for EvalInstructionError {
+    fn from(error: DeviceError) -> Self {
+        match error {
+            DeviceError::Write(e) => EvalInstructionError::Write(e),
+            DeviceError::Read(e) => EvalInstructionError::Read(e),
+        }
+    }
+}
 
 struct NarviePortError {}
 
@@ -55,62 +71,29 @@ impl Debug for NarviePortError {
 }
 impl Error for NarviePortError {}
 
-struct SerialLogger<S: io::Read + io::Write, L: io::Write> {
-    stream: S,
+/* Saves the register file returned by narvie after each instruction. If the
+ * log cannot be written, logging stops but the instruction still succeeds,
+ * as narvie has already run it.
+ */
+struct SerialLogger<D: NarvieDevice, L: io::Write> {
+    device: D,
     logger: Option<L>,
 }
 
-impl<S: io::Read + io::Write, L: io::Write> io::Read for SerialLogger<S, L> {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let bytes_read = self.stream.read(buf)?;
+impl<D: NarvieDevice, L: io::Write> NarvieDevice for SerialLogger<D, L> {
+    fn execute(&mut self, instruction: u32) -> Result<RegisterFile, DeviceError> {
+        let reg_file = self.device.execute(instruction)?;
         if let Some(ref mut logger) = self.logger {
-            logger.write_all(&buf[0..bytes_read])?;
-        }
-        Ok(bytes_read)
-    }
-}
-
-impl<S: io::Read + io::Write, L: io::Write> io::Write for SerialLogger<S, L> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.stream.write(buf)
-    }
-    fn flush(&mut self) -> io::Result<()> {
-        self.stream.flush()
-    }
-}
-
-struct SimulationStream {
-    from_simulation: Receiver<u8>,
-    to_simulation: Sender<u8>,
-}
-
-impl io::Read for SimulationStream {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if buf.len() == 0 {
-            Ok(0)
-        } else {
-            buf[0] = self
-                .from_simulation
-                .recv()
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-            Ok(1)
+            if let Err(e) = logger.write_all(&reg_file.to_le_bytes()) {
+                warn!("Stopped logging register files: {}", e);
+                self.logger = None;
+            }
         }
+        Ok(reg_file)
     }
-}
 
-impl io::Write for SimulationStream {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        if buf.len() == 0 {
-            Ok(0)
-        } else {
-            self.to_simulation
-                .send(buf[0])
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-            Ok(1)
-        }
-    }
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+    fn reset(&mut self) -> Result<bool, DeviceError> {
+        self.device.reset()
     }
 }
 
@@ -120,7 +103,9 @@ fn format_headers(f: &instruction::Format) -> &'static [&'static str] {
     match f {
         instruction::Format::U => &["imm[31:12]", "rd", "opcode"],
         instruction::Format::J => &["imm[20|10:1|11|19:12]", "rd", "opcode"],
-        instruction::Format::I(instruction::ISpecialization::None) => {
+        instruction::Format::I(instruction::ISpecialization::None)
+        | instruction::Format::I(instruction::ISpecialization::Load)
+        | instruction::Format::I(instruction::ISpecialization::NoArgs) => {
             &["imm[11:0]", "rs1", "funct3", "rd", "opcode"]
         }
         instruction::Format::I(instruction::ISpecialization::Csr) => {
@@ -157,6 +142,8 @@ fn binary_block_widths(f: &instruction::Format) -> &'static [u32] {
         instruction::Format::U => &[20, 5, 7],
         instruction::Format::J => &[20, 5, 7],
         instruction::Format::I(instruction::ISpecialization::None)
+        | instruction::Format::I(instruction::ISpecialization::Load)
+        | instruction::Format::I(instruction::ISpecialization::NoArgs)
         | instruction::Format::I(instruction::ISpecialization::Csr)
         | instruction::Format::I(instruction::ISpecialization::Csri) => &[12, 5, 3, 5, 7],
         instruction::Format::B => &[7, 5, 5, 3, 5, 7],
@@ -167,14 +154,49 @@ fn binary_block_widths(f: &instruction::Format) -> &'static [u32] {
     }
 }
 
-fn format_binary_instruction(inst: &Instruction) -> Vec<String> {
-    let widths = binary_block_widths(&inst.to_format());
-    assert!(widths.into_iter().sum::<u32>() == 32);
+/* The compressed equivalents of `format_headers` and `binary_block_widths`.
+ * These widths sum to 16.
+ */
+fn compressed_format_headers(f: &compressed::Format) -> &'static [&'static str] {
+    match f {
+        compressed::Format::CR => &["funct4", "rd/rs1", "rs2", "op"],
+        compressed::Format::CI => &["funct3", "imm", "rd/rs1", "imm", "op"],
+        compressed::Format::CSS => &["funct3", "imm", "rs2", "op"],
+        compressed::Format::CIW => &["funct3", "imm", "rd'", "op"],
+        compressed::Format::CL => &["funct3", "imm", "rs1'", "imm", "rd'", "op"],
+        compressed::Format::CS => &["funct3", "imm", "rs1'", "imm", "rs2'", "op"],
+        compressed::Format::CA => &["funct6", "rd'/rs1'", "funct2", "rs2'", "op"],
+        compressed::Format::CB(compressed::CBSpecialization::Branch) => {
+            &["funct3", "offset", "rs1'", "offset", "op"]
+        }
+        compressed::Format::CB(compressed::CBSpecialization::Arithmetic) => {
+            &["funct3", "imm", "funct2", "rd'/rs1'", "imm", "op"]
+        }
+        compressed::Format::CJ => &["funct3", "jump target", "op"],
+    }
+}
+
+fn compressed_binary_block_widths(f: &compressed::Format) -> &'static [u32] {
+    match f {
+        compressed::Format::CR => &[4, 5, 5, 2],
+        compressed::Format::CI => &[3, 1, 5, 5, 2],
+        compressed::Format::CSS => &[3, 6, 5, 2],
+        compressed::Format::CIW => &[3, 8, 3, 2],
+        compressed::Format::CL | compressed::Format::CS => &[3, 3, 3, 2, 3, 2],
+        compressed::Format::CA => &[6, 3, 2, 3, 2],
+        compressed::Format::CB(compressed::CBSpecialization::Branch) => &[3, 3, 3, 5, 2],
+        compressed::Format::CB(compressed::CBSpecialization::Arithmetic) => &[3, 1, 2, 3, 5, 2],
+        compressed::Format::CJ => &[3, 11, 2],
+    }
+}
 
-    let binary_str = format!("{:032b}", inst.to_u32());
+fn format_binary(word: u32, bit_count: u32, widths: &[u32]) -> Vec<String> {
+    assert!(widths.iter().sum::<u32>() == bit_count);
+
+    let binary_str = format!("{:0width$b}", word, width = bit_count as usize);
 
     widths
-        .into_iter()
+        .iter()
         .fold((vec![], binary_str.as_str()), |(mut p, string), w| {
             let (a, rest) = string.split_at(*w as usize);
             p.push(a.to_string());
@@ -183,7 +205,24 @@ fn format_binary_instruction(inst: &Instruction) -> Vec<String> {
         .0
 }
 
-fn assembly_table(instruction: &Instruction) -> prettytable::Table {
+fn format_binary_instruction(inst: &Instruction) -> Vec<String> {
+    format_binary(inst.to_u32(), 32, binary_block_widths(&inst.to_format()))
+}
+
+fn format_binary_compressed(inst: &CompressedInstruction) -> Vec<String> {
+    format_binary(
+        u32::from(inst.to_u16()),
+        16,
+        compressed_binary_block_widths(&inst.to_format()),
+    )
+}
+
+fn breakdown_table(
+    mnemonic: String,
+    hexadecimal: String,
+    headers: &[&str],
+    blocks: Vec<String>,
+) -> prettytable::Table {
     let breakdown_format =
         prettytable::format::FormatBuilder::from(*prettytable::format::consts::FORMAT_CLEAN)
             .padding(1, 1)
@@ -197,14 +236,14 @@ fn assembly_table(instruction: &Instruction) -> prettytable::Table {
     let titles = prettytable::row![c => "Mnemonic", "Hexadecimal", "Binary"];
 
     let instruction_headers = prettytable::Row::new(
-        format_headers(&instruction.to_format())
-            .into_iter()
-            .map(|s| prettytable::Cell::new_align(&s, prettytable::format::Alignment::CENTER))
+        headers
+            .iter()
+            .map(|s| prettytable::Cell::new_align(s, prettytable::format::Alignment::CENTER))
             .collect(),
     );
 
     let instruction_bits = prettytable::Row::new(
-        format_binary_instruction(&instruction)
+        blocks
             .into_iter()
             .map(|s| prettytable::Cell::new_align(&s, prettytable::format::Alignment::CENTER))
             .collect(),
@@ -215,8 +254,8 @@ fn assembly_table(instruction: &Instruction) -> prettytable::Table {
     binary_breakdown.set_format(breakdown_format);
 
     let mut table = prettytable::Table::init(vec![row![c =>
-        format!("\n{}", instruction),
-        format!("\n{:08X}", instruction.to_u32()),
+        format!("\n{}", mnemonic),
+        format!("\n{}", hexadecimal),
         binary_breakdown,
     ]]);
 
@@ -225,64 +264,792 @@ fn assembly_table(instruction: &Instruction) -> prettytable::Table {
     table
 }
 
-fn reg_file_table(reg_file: &[u32; 32]) -> prettytable::Table {
-    const COLUMNS: u32 = 2;
-    const ROWS: u32 = register::GPR_COUNT / 2;
-    if ROWS * COLUMNS != register::GPR_COUNT {
-        panic!("Expected an even number of registers");
-    }
-    let mut table = prettytable::Table::init(vec![prettytable::Row::new(
-        (0..COLUMNS)
-            .map(|i| {
-                let mut side_table = prettytable::table!(["Name", "ABI", "Value"]);
-                side_table.extend((0..ROWS).map(|j| {
-                    let reg_index = i * ROWS + j;
-                    prettytable::row![
-                        format!("x{}", reg_index),
-                        Register::<()>::from_u32(reg_index).unwrap().abi_name(),
-                        format!("0x{:08X}", reg_file[reg_index as usize]),
-                    ]
-                }));
-
-                side_table.set_format(*prettytable::format::consts::FORMAT_BOX_CHARS);
-                prettytable::cell!(side_table)
-            })
-            .collect::<Vec<prettytable::Cell>>(),
-    )]);
+fn assembly_table(instruction: &Instruction) -> prettytable::Table {
+    breakdown_table(
+        instruction.to_string(),
+        format!("{:08X}", instruction.to_u32()),
+        format_headers(&instruction.to_format()),
+        format_binary_instruction(instruction),
+    )
+}
+
+fn compressed_assembly_table(instruction: &CompressedInstruction) -> prettytable::Table {
+    breakdown_table(
+        instruction.to_string(),
+        format!("{:04X}", instruction.to_u16()),
+        compressed_format_headers(&instruction.to_format()),
+        format_binary_compressed(instruction),
+    )
+}
+
+/* Parses a comma separated list of views such as `hex,signed`.
+ */
+fn parse_numeric_views(views: &str) -> Result<Vec<NumericView>, String> {
+    views
+        .split(',')
+        .map(str::trim)
+        .map(|name| {
+            NUMERIC_VIEWS
+                .iter()
+                .find(|(view_name, _)| view_name.eq_ignore_ascii_case(name))
+                .map(|(_, view)| *view)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = NUMERIC_VIEWS.iter().map(|(n, _)| *n).collect();
+                    format!(
+                        "Unknown view `{}`, expected a comma separated list of {}",
+                        name,
+                        names.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+/* Remembers the register file from the last evaluation so that changes can
+ * be shown.
+ */
+struct RegisterDisplay {
+    previous: Option<RegisterFile>,
+    changed_only: bool,
+    views: Vec<NumericView>,
+}
+
+impl RegisterDisplay {
+    fn print(&mut self, reg_file: RegisterFile) {
+        match &self.previous {
+            Some(previous) if self.changed_only => {
+                if reg_file == *previous {
+                    println!("No registers changed");
+                } else {
+                    reg_file.changes_table(previous, &self.views).printstd();
+                }
+            }
+            previous => {
+                reg_file.table(previous.as_ref(), &self.views).printstd();
+            }
+        }
+        self.previous = Some(reg_file);
+    }
+}
+
+/* Input that is a single 32 bit word in hexadecimal, either `0x00B502B3` or
+ * `00b502b3` as printed by objdump.
+ */
+fn parse_word(input: &str) -> Option<u32> {
+    if let Some(hex) = input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+    {
+        u32::from_str_radix(hex, 16).ok()
+    } else if input.len() == 8 {
+        u32::from_str_radix(input, 16).ok()
+    } else {
+        None
+    }
+}
+
+/* Words are disassembled, anything else is parsed as a mnemonic, which may
+ * be a pseudo-instruction. Also returns whether the input was a
+ * pseudo-instruction.
+ */
+fn parse_input(input: &str) -> Result<(Vec<Instruction>, bool), EvalInstructionError> {
+    if let Some(word) = parse_word(input) {
+        Instruction::from_u32(word)
+            .map(|instruction| (vec![instruction], false))
+            .map_err(EvalInstructionError::Decode)
+    } else if let Some(sequence) = pseudo::expand(input) {
+        sequence
+            .map(|instructions| (instructions, true))
+            .map_err(EvalInstructionError::Parse)
+    } else {
+        pseudo::parse(input)
+            .map(|instructions| (instructions, false))
+            .map_err(EvalInstructionError::Parse)
+    }
+}
+
+fn is_compressed(input: &str) -> bool {
+    matches!(input.get(..2), Some(prefix) if prefix.eq_ignore_ascii_case("c."))
+}
+
+/* Prints how the input is encoded and returns the instructions to send to
+ * narvie, in order. Compressed instructions are shown alongside their 32 bit
+ * expansion and pseudo-instructions alongside the instructions they expand
+ * to, which is what gets evaluated.
+ */
+fn assemble(input: &str) -> Result<Vec<Instruction>, EvalInstructionError> {
+    if is_compressed(input) {
+        let compressed =
+            CompressedInstruction::from_str(input).map_err(EvalInstructionError::Parse)?;
+        compressed_assembly_table(&compressed).printstd();
+        println!("expands to");
+
+        let instruction = compressed.expand();
+        assembly_table(&instruction).printstd();
+        Ok(vec![instruction])
+    } else {
+        let (instructions, expanded) = parse_input(input)?;
+        if expanded {
+            println!("{} expands to", input);
+        }
+        for instruction in &instructions {
+            assembly_table(instruction).printstd();
+        }
+        Ok(instructions)
+    }
+}
+
+/* Sends each instruction to narvie in turn and returns the register file
+ * after each one.
+ */
+fn execute<D: NarvieDevice>(
+    instructions: &[Instruction],
+    device: &mut D,
+) -> Result<Vec<RegisterFile>, EvalInstructionError> {
+    instructions
+        .iter()
+        .map(|instruction| Ok(device.execute(instruction.to_u32())?))
+        .collect()
+}
+
+/* Simulations start again from scratch. narvie over a serial port or tcp
+ * cannot be reset, so only its registers are set to zero, leaving its memory,
+ * CSRs and program counter as they were.
+ */
+fn reset<D: NarvieDevice>(device: &mut D) -> Result<RegisterFile, EvalInstructionError> {
+    if device.reset()? {
+        return Ok(RegisterFile::default());
+    }
+    let zero_all: Vec<Instruction> = (1..register::GPR_COUNT)
+        .map(|i| {
+            Instruction::Addi(instruction::I::new(
+                Register::from_u32(i).unwrap(),
+                Register::ZERO,
+                narvie_isa::immediate::Immediate::from_i32(0).unwrap(),
+            ))
+        })
+        .collect();
+    Ok(*execute(&zero_all, device)?.last().unwrap())
+}
+
+/* Only the register file after the last instruction is printed.
+ */
+fn eval_instruction<D: NarvieDevice>(
+    input: &str,
+    device: &mut D,
+    display: &mut RegisterDisplay,
+) -> Result<(), EvalInstructionError> {
+    let reg_files = execute(&assemble(input)?, device)?;
+    if let Some(reg_file) = reg_files.last() {
+        display.print(*reg_file);
+    }
+    Ok(())
+}
+
+/* The instructions to send to narvie for a line of input, as `assemble`
+ * returns them but without printing anything.
+ */
+fn to_instructions(input: &str) -> Result<Vec<Instruction>, EvalInstructionError> {
+    if is_compressed(input) {
+        CompressedInstruction::from_str(input)
+            .map(|compressed| vec![compressed.expand()])
+            .map_err(EvalInstructionError::Parse)
+    } else {
+        parse_input(input).map(|(instructions, _)| instructions)
+    }
+}
+
+fn json_string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/* A single line of JSON describing an instruction and, when it was
+ * evaluated, the register file after it.
+ */
+fn instruction_json(
+    input: &str,
+    instruction: &Instruction,
+    reg_file: Option<&RegisterFile>,
+) -> String {
+    let fields: Vec<String> = format_headers(&instruction.to_format())
+        .iter()
+        .zip(format_binary_instruction(instruction))
+        .map(|(name, bits)| {
+            format!(
+                "{{\"name\": {}, \"bits\": {}}}",
+                json_string(name),
+                json_string(&bits)
+            )
+        })
+        .collect();
+
+    let registers = match reg_file {
+        Some(reg_file) => format!(
+            "[{}]",
+            reg_file
+                .iter()
+                .map(|(_, value)| value.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        None => "null".to_string(),
+    };
+
+    format!(
+        "{{\"input\": {}, \"mnemonic\": {}, \"hex\": {}, \"fields\": [{}], \"registers\": {}}}",
+        json_string(input),
+        json_string(&instruction.to_string()),
+        json_string(&format!("0x{:08X}", instruction.to_u32())),
+        fields.join(", "),
+        registers
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Output {
+    Table,
+    /* One JSON object per line for each instruction evaluated. */
+    Json,
+}
+
+/* Everything the prompt needs to remember between lines.
+ */
+struct Session {
+    /* `None` when only assembling. */
+    port: Option<SerialLogger<Box<dyn NarvieDevice>, File>>,
+    connection: String,
+    display: RegisterDisplay,
+    output: Output,
+    /* The files that `:load` is evaluating, innermost last. */
+    loading: Vec<PathBuf>,
+}
+
+impl Session {
+    fn eval(&mut self, input: &str) -> Result<(), EvalInstructionError> {
+        match (self.output, &mut self.port) {
+            (Output::Table, Some(port)) => eval_instruction(input, port, &mut self.display),
+            (Output::Table, None) => assemble(input).map(|_| ()),
+            (Output::Json, Some(port)) => {
+                let instructions = to_instructions(input)?;
+                let reg_files = execute(&instructions, port)?;
+                for (instruction, reg_file) in instructions.iter().zip(&reg_files) {
+                    println!("{}", instruction_json(input, instruction, Some(reg_file)));
+                }
+                self.display.previous = reg_files.last().cloned();
+                Ok(())
+            }
+            (Output::Json, None) => {
+                for instruction in to_instructions(input)? {
+                    println!("{}", instruction_json(input, &instruction, None));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/* Prints an error from evaluating `line`. Errors talking to narvie cannot be
+ * recovered from and are returned.
+ */
+fn report(line: &str, error: EvalInstructionError, output: Output) -> Result<(), Box<dyn Error>> {
+    if output == Output::Json {
+        let message = match &error {
+            EvalInstructionError::Parse(e) => e.to_string(),
+            EvalInstructionError::Decode(e) => e.to_string(),
+            EvalInstructionError::Write(e) | EvalInstructionError::Read(e) => e.to_string(),
+        };
+        println!(
+            "{{\"input\": {}, \"error\": {}}}",
+            json_string(line),
+            json_string(&message)
+        );
+        return match error {
+            EvalInstructionError::Write(e) | EvalInstructionError::Read(e) => Err(Box::new(e)),
+            _ => Ok(()),
+        };
+    }
+
+    println!(
+        "Error {}:",
+        match error {
+            EvalInstructionError::Parse(_) => "parsing instruction mnemonic",
+            EvalInstructionError::Decode(_) => "decoding instruction word",
+            EvalInstructionError::Write(_) => "writing to serial port",
+            EvalInstructionError::Read(_) => "reading from serial port",
+        }
+    );
+
+    match error {
+        EvalInstructionError::Parse(parse_error) => {
+            for diagnostic_line in parse_error.render(line).lines() {
+                println!("  {}", diagnostic_line);
+            }
+            Ok(())
+        }
+        EvalInstructionError::Decode(decode_error) => {
+            println!("  {}", decode_error);
+            Ok(())
+        }
+        EvalInstructionError::Write(e) => {
+            println!("  {:?}", e);
+            Err(Box::new(e))
+        }
+        EvalInstructionError::Read(e) => {
+            println!("  {:?}", e);
+            Err(Box::new(e))
+        }
+    }
+}
+
+/* Lines starting with `:` or `.` are commands to narvie rather than
+ * instructions. Each entry is the command, its arguments and what it does.
+ */
+const META_COMMANDS: &[(&str, &str, &str)] = &[
+    (
+        "help",
+        "[mnemonic]",
+        "List these commands or describe an instruction",
+    ),
+    ("regs", "", "Print the register file again"),
+    (
+        "reset",
+        "",
+        "Start a simulation afresh or zero the registers",
+    ),
+    (
+        "view",
+        "VIEWS",
+        "Show register values as hex, signed, unsigned, binary or ascii",
+    ),
+    (
+        "changed-only",
+        "",
+        "Toggle only showing the registers that change",
+    ),
+    ("load", "FILE", "Evaluate each line of a file"),
+    ("save-history", "FILE", "Save the prompt history to a file"),
+    ("status", "", "Show what narvie is connected to"),
+    ("quit", "", "Exit narvie"),
+];
+
+fn meta_command(line: &str) -> Option<(&str, &str)> {
+    let command = line.strip_prefix(':').or_else(|| line.strip_prefix('.'))?;
+    let (name, argument) = command.split_at(command.find(' ').unwrap_or(command.len()));
+    Some((name, argument.trim()))
+}
+
+enum Flow {
+    Continue,
+    /* The line could not be evaluated, which stops a `:load`. */
+    Failed,
+    Quit,
+}
+
+fn print_meta_commands() {
+    let mut table = prettytable::Table::new();
+    for (name, argument, description) in META_COMMANDS {
+        table.add_row(prettytable::row![
+            format!(":{} {}", name, argument),
+            description
+        ]);
+    }
     table.set_format(*prettytable::format::consts::FORMAT_CLEAN);
+    table.printstd();
+}
+
+/* The fields of an instruction format, with the bits each one occupies.
+ */
+fn encoding_table(format: &instruction::Format) -> prettytable::Table {
+    let headers = format_headers(format);
+    let widths = binary_block_widths(format);
+
+    let mut high = 32;
+    let bits = widths.iter().map(|width| {
+        let low = high - width;
+        let range = if *width == 1 {
+            format!("{}", low)
+        } else {
+            format!("{}:{}", high - 1, low)
+        };
+        high = low;
+        range
+    });
+
+    let mut table = prettytable::Table::init(vec![
+        prettytable::Row::new(
+            headers
+                .iter()
+                .map(|s| prettytable::Cell::new_align(s, prettytable::format::Alignment::CENTER))
+                .collect(),
+        ),
+        prettytable::Row::new(
+            bits.map(|s| prettytable::Cell::new_align(&s, prettytable::format::Alignment::CENTER))
+                .collect(),
+        ),
+    ]);
+    table.set_format(*prettytable::format::consts::FORMAT_BOX_CHARS);
     table
 }
 
-fn eval_instruction<S>(mnemonic: &str, port: &mut S) -> Result<(), EvalInstructionError>
-where
-    S: io::Read + io::Write,
-{
-    let instruction = Instruction::from_str(mnemonic).map_err(EvalInstructionError::Parse)?;
+fn print_instruction_help(mnemonic: &str) {
+    if let Some(metadata) = instruction::metadata(mnemonic) {
+        let format = &metadata.format;
+        println!("{} {}", metadata.mnemonic, format.operands());
+        println!("    {}", metadata.description);
+        println!();
+        println!("Format: {}", format);
+
+        let registers: Vec<&str> = format
+            .operands()
+            .split(&[',', '(', ')'][..])
+            .map(str::trim)
+            .filter(|operand| operand.starts_with("rd") || operand.starts_with("rs"))
+            .collect();
+        let immediates = format.immediates();
+        if !registers.is_empty() || !immediates.is_empty() {
+            println!("Operands:");
+            if !registers.is_empty() {
+                println!("    {}: any of x0..x31", registers.join(", "));
+            }
+            for immediate in immediates {
+                println!("    {}", immediate);
+            }
+        }
+        println!("Encoding:");
+        encoding_table(format).printstd();
+    } else if pseudo::MNEMONICS.contains(&mnemonic) || compressed::MNEMONICS.contains(&mnemonic) {
+        println!(
+            "`{}` expands to other instructions, which narvie shows when it is entered",
+            mnemonic
+        );
+    } else {
+        println!("No help for `{}`", mnemonic);
+    }
+}
 
-    assembly_table(&instruction).printstd();
+fn dispatch_meta_command(
+    name: &str,
+    argument: &str,
+    session: &mut Session,
+    rl: &mut Editor<NarvieHelper>,
+) -> Result<Flow, Box<dyn Error>> {
+    match name {
+        "help" if argument.is_empty() => print_meta_commands(),
+        "help" => print_instruction_help(argument),
+        "regs" => match (&session.port, session.display.previous) {
+            (None, _) => println!("Registers are not available when only assembling"),
+            (Some(_), None) => println!("No instructions have been evaluated yet"),
+            (Some(_), Some(reg_file)) => {
+                reg_file.table(None, &session.display.views).printstd();
+            }
+        },
+        "reset" => match &mut session.port {
+            None => println!("Registers are not available when only assembling"),
+            Some(port) => match reset(port) {
+                Ok(reg_file) => session.display.print(reg_file),
+                Err(e) => report(":reset", e, session.output)?,
+            },
+        },
+        "view" => match parse_numeric_views(argument) {
+            Ok(views) => session.display.views = views,
+            Err(e) => println!("{}", e),
+        },
+        "changed-only" => {
+            session.display.changed_only = !session.display.changed_only;
+            println!(
+                "Showing {} registers",
+                if session.display.changed_only {
+                    "only changed"
+                } else {
+                    "all"
+                }
+            );
+        }
+        "load" => return load(argument, session, rl),
+        "save-history" => match rl.save_history(argument) {
+            Ok(()) => println!("Saved history to {}", argument),
+            Err(e) => println!("Could not save history to {}: {}", argument, e),
+        },
+        "status" => println!("{}", session.connection),
+        "quit" => return Ok(Flow::Quit),
+        _ => {
+            println!(
+                "Unknown command `{}`, type :help for a list of commands",
+                name
+            );
+            return Ok(Flow::Failed);
+        }
+    }
+    Ok(Flow::Continue)
+}
 
-    port.write_all(&instruction.to_u32().to_le_bytes())
-        .map_err(EvalInstructionError::Write)?;
+/* Evaluates each line of a file, stopping at the first line that cannot be
+ * evaluated as `--script` does. A file that is already being loaded is not
+ * loaded again, so that files cannot load themselves or each other forever.
+ */
+fn load(
+    path: &str,
+    session: &mut Session,
+    rl: &mut Editor<NarvieHelper>,
+) -> Result<Flow, Box<dyn Error>> {
+    let script = match fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(e) => {
+            println!("Could not read {}: {}", path, e);
+            return Ok(Flow::Failed);
+        }
+    };
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    if session.loading.contains(&canonical) {
+        println!("Not loading {} as it is already being loaded", path);
+        return Ok(Flow::Failed);
+    }
 
-    let mut reg_file = [0; 32];
+    session.loading.push(canonical);
+    let flow = load_lines(path, &script, session, rl);
+    session.loading.pop();
+    flow
+}
 
-    for i in 0..32 {
-        let mut buf = [0; 4];
-        port.read_exact(&mut buf)
-            .map_err(EvalInstructionError::Read)?;
-        reg_file[i] = u32::from_le_bytes(buf);
+fn load_lines(
+    path: &str,
+    script: &str,
+    session: &mut Session,
+    rl: &mut Editor<NarvieHelper>,
+) -> Result<Flow, Box<dyn Error>> {
+    for (line_number, line) in script_lines(script) {
+        if session.output == Output::Table {
+            println!("> {}", line);
+        }
+        match eval_line(line, session, rl)? {
+            Flow::Continue => {}
+            Flow::Failed => {
+                println!("Stopped loading {} at line {}", path, line_number);
+                return Ok(Flow::Failed);
+            }
+            Flow::Quit => return Ok(Flow::Quit),
+        }
     }
+    Ok(Flow::Continue)
+}
 
-    reg_file_table(&reg_file).printstd();
+fn eval_line(
+    line: &str,
+    session: &mut Session,
+    rl: &mut Editor<NarvieHelper>,
+) -> Result<Flow, Box<dyn Error>> {
+    if let Some((name, argument)) = meta_command(line) {
+        dispatch_meta_command(name, argument, session, rl)
+    } else {
+        match session.eval(line) {
+            Ok(()) => Ok(Flow::Continue),
+            Err(error) => {
+                report(line, error, session.output)?;
+                Ok(Flow::Failed)
+            }
+        }
+    }
+}
+
+/* The lines of a script paired with their line numbers, without comments
+ * (from `#` to the end of the line) or blank lines.
+ */
+fn script_lines(script: &str) -> impl Iterator<Item = (usize, &str)> {
+    script
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+struct ScriptError {
+    line_number: usize,
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Script stopped at line {}", self.line_number)
+    }
+}
 
+impl Debug for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+impl Error for ScriptError {}
+
+/* The script given with `--script`, or stdin when it is not a terminal.
+ */
+fn read_script(matches: &clap::ArgMatches) -> Option<Result<String, Box<dyn Error>>> {
+    if let Some(path) = matches.value_of("script") {
+        Some(fs::read_to_string(path).map_err(|e| {
+            error!("Could not read script {}", path);
+            Box::new(e).into()
+        }))
+    } else if !atty::is(atty::Stream::Stdin) {
+        let mut script = String::new();
+        Some(
+            io::stdin()
+                .read_to_string(&mut script)
+                .map(|_| script)
+                .map_err(|e| Box::new(e).into()),
+        )
+    } else {
+        None
+    }
+}
+
+/* Evaluates each instruction in `script` without prompting, stopping at the
+ * first error.
+ */
+fn run_script(session: &mut Session, script: &str) -> Result<(), Box<dyn Error>> {
+    for (line_number, line) in script_lines(script) {
+        if session.output == Output::Table {
+            println!("> {}", line);
+        }
+        if let Err(error) = session.eval(line) {
+            report(line, error, session.output)?;
+            return Err(Box::new(ScriptError { line_number }));
+        }
+    }
     Ok(())
 }
 
-fn run<'a, F>(mut evaluator: F, history_file_path: Option<&'a Path>) -> Result<(), Box<dyn Error>>
+/* Runs the instructions in `script` through two simulation backends, given as
+ * `left,right`, and prints where their register files first differ along
+ * with the fewest instructions found that still show the difference.
+ */
+fn run_differential(backends: &str, script: &str) -> Result<(), Box<dyn Error>> {
+    let (left, right) = match backends.split(',').collect::<Vec<_>>()[..] {
+        [left, right] => (left, right),
+        _ => {
+            return Err(format!(
+                "expected two backends such as verilator,model, found `{}`",
+                backends
+            )
+            .into())
+        }
+    };
+
+    let mut instructions = Vec::new();
+    for (line_number, line) in script_lines(script) {
+        match to_instructions(line) {
+            Ok(words) => instructions.extend(words.iter().map(Instruction::to_u32)),
+            Err(error) => {
+                report(line, error, Output::Table)?;
+                return Err(Box::new(ScriptError { line_number }));
+            }
+        }
+    }
+
+    compare(&instructions, (left, right), || {
+        Ok((simulation(left)?, simulation(right)?))
+    })
+}
+
+/* Prints where the register files from two backends, named in `names`, first
+ * differ along with the fewest instructions found that still show the
+ * difference. `start` starts a fresh pair of backends.
+ */
+fn compare<F, L, R>(
+    instructions: &[u32],
+    names: (&str, &str),
+    mut start: F,
+) -> Result<(), Box<dyn Error>>
 where
-    for<'b> F: FnMut(&'b str) -> Result<(), EvalInstructionError>,
+    F: FnMut() -> Result<(L, R), Box<dyn Error>>,
+    L: NarvieDevice,
+    R: NarvieDevice,
 {
-    let mut rl = Editor::<()>::new();
+    let (left, right) = names;
+    let (mut left_port, mut right_port) = start()?;
+    match differential::first_divergence(instructions, &mut left_port, &mut right_port)? {
+        None => {
+            println!(
+                "{} and {} agree on all {} instructions",
+                left,
+                right,
+                instructions.len()
+            );
+            Ok(())
+        }
+        Some(divergence) => {
+            println!("Comparing {} (left) with {} (right)", left, right);
+            print!("{}", divergence);
+
+            let reproducer = differential::minimize(instructions, start)?;
+            println!("Reproduced by {} instruction(s):", reproducer.len());
+            for word in reproducer {
+                println!("  {}", differential::describe(word));
+            }
+            Err(format!("{} and {} differ", left, right).into())
+        }
+    }
+}
+
+/* Fails unless a `nop` leaves every register of `device` zero, as it does
+ * for a newly started simulation.
+ */
+fn check_fresh<D: NarvieDevice>(device: &mut D) -> Result<(), Box<dyn Error>> {
+    const NOP: u32 = 0x0000_0013;
+    if device.execute(NOP)? == RegisterFile::default() {
+        Ok(())
+    } else {
+        Err(
+            "narvie does not start with every register zero. Fuzz a simulation with \
+             `--simulate` or a `narvie-processor --multi-client` server, which start \
+             afresh on every connection"
+                .into(),
+        )
+    }
+}
+
+/* Checks narvie against a newly started software model with `count` random
+ * instructions. Failing sequences are shrunk by calling `connect` again for
+ * each attempt, so every connection must start narvie afresh.
+ */
+fn fuzz<F, D>(count: usize, seed: u64, mut connect: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut() -> Result<D, Box<dyn Error>>,
+    D: NarvieDevice,
+{
+    println!("Fuzzing with {} instructions from seed {}", count, seed);
+
+    let mut rng = random::Rng::new(seed);
+    let instructions: Vec<u32> = (0..count)
+        .map(|_| random::instruction(&mut rng).to_u32())
+        .collect();
+
+    compare(&instructions, ("narvie", "model"), || {
+        let mut narvie = connect()?;
+        let mut model = Model::new();
+        check_fresh(&mut narvie)?;
+        check_fresh(&mut model)?;
+        Ok((narvie, model))
+    })
+}
+
+fn run_fuzz(matches: &clap::ArgMatches, count: usize, seed: u64) -> Result<(), Box<dyn Error>> {
+    fuzz(count, seed, || narvie_port(matches))
+}
+
+fn run(session: &mut Session, history_file_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let mut rl = Editor::<NarvieHelper>::new();
+    rl.set_helper(Some(NarvieHelper::new()));
 
     if let Some(file) = history_file_path {
         if rl.load_history(&file).is_err() {
@@ -302,29 +1069,8 @@ where
                 }
                 let line = line.trim();
                 if !line.is_empty() {
-                    if let Err(error) = evaluator(line) {
-                        println!(
-                            "Error {}:",
-                            match error {
-                                EvalInstructionError::Parse(_) => "parsing instruction mnemonic",
-                                EvalInstructionError::Write(_) => "writing to serial port",
-                                EvalInstructionError::Read(_) => "reading from serial port",
-                            }
-                        );
-
-                        match error {
-                            EvalInstructionError::Parse(parse_error) => {
-                                println!("  {:?}", parse_error)
-                            }
-                            EvalInstructionError::Write(e) => {
-                                println!("  {:?}", e);
-                                return Err(Box::new(e));
-                            }
-                            EvalInstructionError::Read(e) => {
-                                println!("  {:?}", e);
-                                return Err(Box::new(e));
-                            }
-                        };
+                    if let Flow::Quit = eval_line(line, session, &mut rl)? {
+                        return Ok(());
                     }
                 }
             }
@@ -334,39 +1080,84 @@ where
         }
     }
 }
-fn narvie_port(matches: &clap::ArgMatches) -> Result<Box<dyn ReadWrite>, Box<dyn Error>> {
-    if matches.is_present("simulate") {
-        let (send1, recv1) = mpsc::channel();
-        let (send2, recv2) = mpsc::channel();
 
-        thread::spawn(|| {
-            narvie_processor::run_narvie(send1, recv2);
-        });
+/* The Verilator simulation is only available when built with the
+ * `verilator` feature.
+ */
+const DEFAULT_BACKEND: &str = if cfg!(feature = "verilator") {
+    "verilator"
+} else {
+    "model"
+};
 
-        Ok(Box::new(SimulationStream {
-            from_simulation: recv1,
-            to_simulation: send2,
-        }))
-    } else if let Some(tcp_port) = matches.value_of("tcp-port") {
-        let tcp_port = tcp_port.parse::<u16>().map_err(|e| {
-            error!("Port for tcp must be a positive integer");
-            Box::new(e)
-        })?;
+/* A description of the connection for `:status`.
+ */
+fn connection_description(matches: &clap::ArgMatches) -> String {
+    if matches.is_present("assemble-only") {
+        "Only assembling, not connected to a narvie processor".to_string()
+    } else if matches.is_present("simulate") {
+        format!(
+            "Connected to a {} simulation of the narvie processor",
+            matches.value_of("backend").unwrap_or("?")
+        )
+    } else if let Some(tcp) = matches.value_of("tcp") {
+        format!("Connected over tcp to {}", tcp_address(tcp))
+    } else {
+        format!(
+            "Connected to serial port {} at {} baud",
+            matches.value_of("address").unwrap_or("?"),
+            matches.value_of("baud").unwrap_or("?")
+        )
+    }
+}
 
-        TcpStream::connect(("localhost", tcp_port))
+fn simulation(backend: &str) -> Result<Box<dyn NarvieDevice>, Box<dyn Error>> {
+    match backend {
+        #[cfg(feature = "verilator")]
+        "verilator" => Ok(Box::new(Simulation::start())),
+        "model" => Ok(Box::new(Model::new())),
+        _ => {
+            error!(
+                "
+This narvie CLI was built without the Verilator simulation of the narvie
+processor. Use `--backend model` to simulate narvie in software, or install
+narvie again with the default features: `$ cargo install narvie-cli`."
+            );
+            Err(Box::new(NarviePortError {}))
+        }
+    }
+}
+
+/* `--tcp` takes either `host:port` or, as it used to, just a port on
+ * localhost.
+ */
+fn tcp_address(tcp: &str) -> String {
+    if tcp.parse::<u16>().is_ok() {
+        format!("localhost:{}", tcp)
+    } else {
+        tcp.to_string()
+    }
+}
+
+fn narvie_port(matches: &clap::ArgMatches) -> Result<Box<dyn NarvieDevice>, Box<dyn Error>> {
+    if matches.is_present("simulate") {
+        simulation(matches.value_of("backend").unwrap())
+    } else if let Some(tcp) = matches.value_of("tcp") {
+        let address = tcp_address(tcp);
+
+        narvie_isa::device::tcp(address.as_str())
             .map_err(|e| {
                 error!(
                     "
-narvie cannot connect to tcp port {}!
+narvie cannot connect over tcp to {}!
 Check that a simulation of the narvie processor is running and the that you
-are using the correct port address. Then run the narvie CLI again.",
-                    tcp_port
+are using the correct host and port. Then run the narvie CLI again.",
+                    address
                 );
                 debug!("Error details: {:?}", e);
                 Box::new(e).into()
             })
-            .map(Box::new)
-            .map(|b| Box::<dyn ReadWrite>::from(b))
+            .map(|device| Box::new(device) as Box<dyn NarvieDevice>)
     } else {
         let address = matches.value_of("address").ok_or_else(|| {
             if let Ok(ports) = serialport::available_ports() {
@@ -403,39 +1194,28 @@ Maybe try one of these:"
                 Box::new(NarviePortError {})
             })?;
 
-        serialport::open_with_settings(
-            address,
-            &serialport::SerialPortSettings {
-                baud_rate: baud,
-                data_bits: serialport::DataBits::Eight,
-                flow_control: serialport::FlowControl::None,
-                parity: serialport::Parity::None,
-                stop_bits: serialport::StopBits::One,
-                timeout: Duration::from_millis(500),
-            },
-        )
-        .map_err(|e: serialport::Error| {
-            let header = "Cannot connect to narvie processor!";
-            if e.description == "Permission denied" {
-                error!(
-                    "{}
+        device::serial(address, baud)
+            .map_err(|e: serialport::Error| {
+                let header = "Cannot connect to narvie processor!";
+                if e.description == "Permission denied" {
+                    error!(
+                        "{}
 \tIt may be that narvie does not have permission to access your serial port.
 \tTry running `$ sudo chmod 666 {}`",
-                    header, address,
-                );
-            } else {
-                error!(
-                    "{}
+                        header, address,
+                    );
+                } else {
+                    error!(
+                        "{}
 \tCheck the processor is running and the that you are using the
 \tcorrect address. Then run the narvie CLI again.",
-                    header
-                );
-            }
-            debug!("Error details: {:?}", e);
-            Box::new(e).into()
-        })
-        .map(Box::new)
-        .map(|b| Box::<dyn ReadWrite>::from(b))
+                        header
+                    );
+                }
+                debug!("Error details: {:?}", e);
+                Box::new(e).into()
+            })
+            .map(|device| Box::new(device) as Box<dyn NarvieDevice>)
     }
 }
 
@@ -472,17 +1252,32 @@ fn main() {
                 .help("serial port port address."),
         )
         .arg(
-            Arg::with_name("tcp-port")
-                .value_name("PORT")
+            Arg::with_name("tcp")
+                .value_name("[HOST:]PORT")
                 .takes_value(true)
                 .long("tcp")
-                .help("Listen over tcp at port PORT instead of a serialport."),
+                .help(
+                    "Connect over tcp to a narvie simulation at HOST:PORT instead of a \
+                     serialport. HOST defaults to localhost.",
+                ),
         )
         .arg(
             Arg::with_name("simulate")
                 .long("simulate")
                 .help("Run simulation of the narvie processor."),
         )
+        .arg(
+            Arg::with_name("backend")
+                .long("backend")
+                .value_name("BACKEND")
+                .takes_value(true)
+                .possible_values(&["verilator", "model"])
+                .default_value(DEFAULT_BACKEND)
+                .help(
+                    "How to simulate the narvie processor, with Verilator or a software model \
+                     that needs no C++ toolchain.",
+                ),
+        )
         .arg(
             Arg::with_name("baud")
                 .default_value("9600")
@@ -491,6 +1286,75 @@ fn main() {
                 .long("baud")
                 .help("baud rate"),
         )
+        .arg(
+            Arg::with_name("changed-only")
+                .long("changed-only")
+                .help("Only show the registers whose values change."),
+        )
+        .arg(
+            Arg::with_name("view")
+                .long("view")
+                .value_name("VIEWS")
+                .takes_value(true)
+                .default_value("hex")
+                .help(
+                    "How to show register values, a comma separated list of hex, signed, \
+                     unsigned, binary and ascii.",
+                ),
+        )
+        .arg(
+            Arg::with_name("script")
+                .long("script")
+                .value_name("FILE")
+                .takes_value(true)
+                .help(
+                    "Evaluate each line of FILE instead of prompting, stopping at the first \
+                     error. Instructions are also read from stdin when it is not a terminal.",
+                ),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .possible_values(&["table", "json"])
+                .default_value("table")
+                .help(
+                    "Print tables, or a line of JSON for each instruction evaluated with its \
+                     encoding and the register file after it.",
+                ),
+        )
+        .arg(
+            Arg::with_name("differential")
+                .long("differential")
+                .value_name("LEFT,RIGHT")
+                .takes_value(true)
+                .help(
+                    "Run the instructions from --script or stdin through two simulation \
+                     backends, such as verilator,model, and report the first instruction after \
+                     which their registers differ.",
+                ),
+        )
+        .arg(
+            Arg::with_name("fuzz")
+                .long("fuzz")
+                .value_name("COUNT")
+                .takes_value(true)
+                .conflicts_with("address")
+                .help(
+                    "Send COUNT random instructions to narvie and check the registers against \
+                     the software model. narvie must start afresh on every connection, as \
+                     with --simulate or a narvie-processor --multi-client server.",
+                ),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("SEED")
+                .takes_value(true)
+                .requires("fuzz")
+                .help("Seed for the random instructions of --fuzz, to reproduce a run."),
+        )
         .arg(
             Arg::with_name("assemble-only")
                 .long("assemble-only")
@@ -498,17 +1362,46 @@ fn main() {
         )
         .get_matches();
 
-    (if matches.is_present("assemble-only") {
-        run(
-            move |mnemonic| {
-                let instruction =
-                    Instruction::from_str(mnemonic).map_err(EvalInstructionError::Parse)?;
+    let views = parse_numeric_views(matches.value_of("view").unwrap()).unwrap_or_else(|e| {
+        error!("{}", e);
+        process::exit(1)
+    });
 
-                assembly_table(&instruction).printstd();
-                Ok(())
+    if let Some(backends) = matches.value_of("differential") {
+        read_script(&matches)
+            .unwrap_or_else(|| {
+                Err("--differential reads instructions from --script or stdin".into())
+            })
+            .and_then(|script| run_differential(backends, &script))
+            .unwrap_or_else(|e| {
+                error!("{}", e);
+                process::exit(1)
+            });
+        return;
+    }
+
+    if let Some(count) = matches.value_of("fuzz") {
+        let seed = matches.value_of("seed").map_or_else(
+            || {
+                Ok(SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_nanos() as u64))
             },
-            history_file_path,
-        )
+            str::parse,
+        );
+        (match (count.parse(), seed) {
+            (Ok(count), Ok(seed)) => run_fuzz(&matches, count, seed),
+            _ => Err("--fuzz and --seed must be positive integers".into()),
+        })
+        .unwrap_or_else(|e| {
+            error!("{}", e);
+            process::exit(1)
+        });
+        return;
+    }
+
+    let port = if matches.is_present("assemble-only") {
+        None
     } else {
         let logger = log_file.and_then(|p| {
             File::create(&p)
@@ -520,18 +1413,208 @@ fn main() {
                 .ok()
         });
 
-        let mut stream = SerialLogger {
-            stream: narvie_port(&matches).unwrap_or_else(|_| process::exit(1)),
-            logger: logger,
-        };
+        Some(SerialLogger {
+            device: narvie_port(&matches).unwrap_or_else(|_| process::exit(1)),
+            logger,
+        })
+    };
 
-        run(
-            move |inst| eval_instruction(inst, &mut stream),
-            history_file_path,
-        )
+    let mut session = Session {
+        port,
+        connection: connection_description(&matches),
+        display: RegisterDisplay {
+            previous: None,
+            changed_only: matches.is_present("changed-only"),
+            views,
+        },
+        output: match matches.value_of("output") {
+            Some("json") => Output::Json,
+            _ => Output::Table,
+        },
+        loading: Vec::new(),
+    };
+
+    (match read_script(&matches) {
+        Some(script) => script.and_then(|script| run_script(&mut session, &script)),
+        None => run(&mut session, history_file_path),
     })
     .unwrap_or_else(|e| {
-        error!("Unrecognised error: {}", e);
+        error!("{}", e);
         process::exit(1)
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use differential::sub_adds;
+    use narvie_isa::device::MockDevice;
+
+    /* Only assembles and prints JSON, which needs neither narvie nor a
+     * terminal.
+     */
+    fn json_session() -> Session {
+        Session {
+            port: None,
+            connection: String::new(),
+            display: RegisterDisplay {
+                previous: None,
+                changed_only: false,
+                views: Vec::new(),
+            },
+            output: Output::Json,
+            loading: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn load_stops_at_errors_and_cycles() {
+        let dir = std::env::temp_dir().join(format!("narvie-load-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.s");
+        let b = dir.join("b.s");
+        let load_file = |path: &Path| {
+            let mut rl = Editor::<NarvieHelper>::new();
+            let mut session = json_session();
+            let flow = load(path.to_str().unwrap(), &mut session, &mut rl).unwrap();
+            assert!(session.loading.is_empty());
+            flow
+        };
+
+        fs::write(&a, format!("nop\n:load {}\n", b.display())).unwrap();
+        fs::write(&b, format!(":load {}\nnop\n", a.display())).unwrap();
+        assert!(matches!(load_file(&a), Flow::Failed));
+
+        fs::write(&b, "addi a0, zero\n:quit\n").unwrap();
+        assert!(matches!(load_file(&b), Flow::Failed));
+
+        fs::write(&b, "nop # comment\n\n:quit\n").unwrap();
+        assert!(matches!(load_file(&b), Flow::Quit));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reset_simulations_and_serial() {
+        let mut model = Model::new();
+        for line in &["addi a0, zero, 5", "sw a0, 8(zero)"] {
+            execute(&parse_input(line).unwrap().0, &mut model).unwrap();
+        }
+        assert_eq!(reset(&mut model).unwrap(), RegisterFile::default());
+        let reg_files = execute(&parse_input("lw a1, 8(zero)").unwrap().0, &mut model).unwrap();
+        assert_eq!(reg_files[0]["a1"], 0);
+
+        let mut serial = MockDevice::new(|_| RegisterFile::default());
+        assert_eq!(reset(&mut serial).unwrap(), RegisterFile::default());
+        assert_eq!(serial.received.len(), 31);
+        assert!(serial.received.iter().all(|i| i & 0xFFFF_F07F == 0x13));
+    }
+
+    #[test]
+    fn log_errors_do_not_fail_instructions() {
+        struct Full;
+
+        impl io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "no space left"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut logger = SerialLogger {
+            device: MockDevice::new(|_| RegisterFile::default()),
+            logger: Some(Full),
+        };
+        assert!(logger.execute(0x0000_0013).is_ok());
+        assert!(logger.logger.is_none());
+        assert!(logger.execute(0x0000_0013).is_ok());
+    }
+
+    #[test]
+    fn script_lines_skip_comments_and_blanks() {
+        let script = "# setup\nli a0, 5\n\n   \naddi a0, a0, 1 # increment\n  nop  \n";
+        assert_eq!(
+            script_lines(script).collect::<Vec<_>>(),
+            vec![(2, "li a0, 5"), (5, "addi a0, a0, 1"), (6, "nop")]
+        );
+    }
+
+    #[test]
+    fn run_script_stops_at_first_error() {
+        let error = run_script(&mut json_session(), "nop\n\naddi a0, zero\nnop\n").unwrap_err();
+        assert_eq!(error.to_string(), "Script stopped at line 3");
+        assert!(run_script(&mut json_session(), "nop\nli a0, 5\n").is_ok());
+    }
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string("li a0, 5"), r#""li a0, 5""#);
+        assert_eq!(
+            json_string("say \"hi\"\\\n\t\u{1}é"),
+            r#""say \"hi\"\\\n\t\u0001é""#
+        );
+    }
+
+    #[test]
+    fn instruction_json_shape() {
+        let add = Instruction::from_str("add a0, a1, a2").unwrap();
+        assert_eq!(
+            instruction_json("add a0, a1, a2", &add, None),
+            concat!(
+                r#"{"input": "add a0, a1, a2", "mnemonic": "add x10,x11,x12", "hex": "0x00C58533", "#,
+                r#""fields": [{"name": "funct7", "bits": "0000000"}, {"name": "rs2", "bits": "01100"}, "#,
+                r#"{"name": "rs1", "bits": "01011"}, {"name": "funct3", "bits": "000"}, "#,
+                r#"{"name": "rd", "bits": "01010"}, {"name": "opcode", "bits": "0110011"}], "#,
+                r#""registers": null}"#
+            )
+        );
+
+        let mut registers = [0; 32];
+        registers[10] = 7;
+        let json = instruction_json("add a0, a1, a2", &add, Some(&RegisterFile::new(registers)));
+        assert!(json.ends_with(
+            r#""registers": [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]}"#
+        ));
+    }
+
+    #[test]
+    fn fuzz_against_model() {
+        assert!(fuzz(2000, 1, || Ok(Model::new())).is_ok());
+
+        let error = fuzz(2000, 1, || Ok(sub_adds())).unwrap_err();
+        assert_eq!(error.to_string(), "narvie and model differ");
+
+        /* A processor that has already been used is rejected rather than
+         * reported as differing from the model.
+         */
+        let error = fuzz(2000, 1, || {
+            let mut used = Model::new();
+            used.execute(0x0050_0513); // addi a0, zero, 5
+            Ok(used)
+        })
+        .unwrap_err();
+        assert!(error.to_string().starts_with("narvie does not start"));
+    }
+
+    #[test]
+    fn compare_minimizes_divergence() {
+        let program = [
+            0x0050_0513, // addi a0, zero, 5
+            0x0030_0593, // addi a1, zero, 3
+            0x40B5_06B3, // sub a3, a0, a1
+        ];
+        let error = compare(&program, ("model", "broken"), || {
+            Ok((Model::new(), sub_adds()))
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "model and broken differ");
+
+        assert!(compare(&program, ("model", "model"), || Ok((
+            Model::new(),
+            Model::new()
+        )))
+        .is_ok());
+    }
+}
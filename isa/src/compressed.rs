@@ -0,0 +1,975 @@
+use crate::immediate::{Constraints, Immediate};
+use crate::instruction::{get_memory_argument, parse_help, split_mnemonic, Error, Instruction};
+use crate::instruction::{invalid_instruction_name, parse_immediate, parse_register};
+use crate::instruction::{InvalidArgument, Load, Shift, B, I, J, R, S, U};
+use crate::register::{GetRegisterError, Rd, Register, Rs1, Rs2};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub struct AddiSpnImmediate;
+#[derive(Debug)]
+pub struct WordOffset;
+#[derive(Debug)]
+pub struct StackWordOffset;
+#[derive(Debug)]
+pub struct SixBit;
+#[derive(Debug)]
+pub struct NonZeroSixBit;
+#[derive(Debug)]
+pub struct StackAdjustment;
+#[derive(Debug)]
+pub struct ShiftAmount;
+#[derive(Debug)]
+pub struct BranchOffset;
+#[derive(Debug)]
+pub struct JumpOffset;
+
+impl Constraints for AddiSpnImmediate {
+    const MAX: i32 = (1 << 10) - 4;
+    const MIN: i32 = 4;
+    const EVEN: bool = true;
+    const MULTIPLE_OF: i32 = 4;
+    fn from_special_string(_: &str) -> Option<Immediate<Self>> {
+        None
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", value.to_i32())
+    }
+}
+
+impl Constraints for WordOffset {
+    const MAX: i32 = (1 << 7) - 4;
+    const MIN: i32 = 0;
+    const EVEN: bool = true;
+    const MULTIPLE_OF: i32 = 4;
+    fn from_special_string(_: &str) -> Option<Immediate<Self>> {
+        None
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", value.to_i32())
+    }
+}
+
+impl Constraints for StackWordOffset {
+    const MAX: i32 = (1 << 8) - 4;
+    const MIN: i32 = 0;
+    const EVEN: bool = true;
+    const MULTIPLE_OF: i32 = 4;
+    fn from_special_string(_: &str) -> Option<Immediate<Self>> {
+        None
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", value.to_i32())
+    }
+}
+
+impl Constraints for SixBit {
+    const MAX: i32 = (1 << 5) - 1;
+    const MIN: i32 = -(1 << 5);
+    const EVEN: bool = false;
+    fn from_special_string(_: &str) -> Option<Immediate<Self>> {
+        None
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", value.to_i32())
+    }
+}
+
+impl Constraints for NonZeroSixBit {
+    const MAX: i32 = (1 << 5) - 1;
+    const MIN: i32 = -(1 << 5);
+    const EVEN: bool = false;
+    const NON_ZERO: bool = true;
+    fn from_special_string(_: &str) -> Option<Immediate<Self>> {
+        None
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", value.to_i32())
+    }
+}
+
+impl Constraints for StackAdjustment {
+    const MAX: i32 = (1 << 9) - 16;
+    const MIN: i32 = -(1 << 9);
+    const EVEN: bool = true;
+    const MULTIPLE_OF: i32 = 16;
+    const NON_ZERO: bool = true;
+    fn from_special_string(_: &str) -> Option<Immediate<Self>> {
+        None
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", value.to_i32())
+    }
+}
+
+impl Constraints for ShiftAmount {
+    const MAX: i32 = 31;
+    const MIN: i32 = 1;
+    const EVEN: bool = false;
+    fn from_special_string(_: &str) -> Option<Immediate<Self>> {
+        None
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", value.to_i32())
+    }
+}
+
+impl Constraints for BranchOffset {
+    const MAX: i32 = (1 << 8) - 2;
+    const MIN: i32 = -(1 << 8);
+    const EVEN: bool = true;
+    fn from_special_string(_: &str) -> Option<Immediate<Self>> {
+        None
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", value.to_i32())
+    }
+}
+
+impl Constraints for JumpOffset {
+    const MAX: i32 = (1 << 11) - 2;
+    const MIN: i32 = -(1 << 11);
+    const EVEN: bool = true;
+    fn from_special_string(_: &str) -> Option<Immediate<Self>> {
+        None
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", value.to_i32())
+    }
+}
+
+/* The RV32C instructions (excluding the floating point loads and stores).
+ *
+ * Registers are checked when parsing: the three bit register fields can only
+ * hold x8 to x15 and some instructions reserve x0 or sp.
+ */
+#[derive(Debug)]
+pub enum CompressedInstruction {
+    Addi4spn(Register<Rd>, Immediate<AddiSpnImmediate>),
+    Lw(Register<Rd>, Register<Rs1>, Immediate<WordOffset>),
+    Sw(Register<Rs2>, Register<Rs1>, Immediate<WordOffset>),
+    Nop,
+    Addi(Register<Rd>, Immediate<NonZeroSixBit>),
+    Jal(Immediate<JumpOffset>),
+    Li(Register<Rd>, Immediate<SixBit>),
+    Addi16sp(Immediate<StackAdjustment>),
+    Lui(Register<Rd>, Immediate<NonZeroSixBit>),
+    Srli(Register<Rd>, Immediate<ShiftAmount>),
+    Srai(Register<Rd>, Immediate<ShiftAmount>),
+    Andi(Register<Rd>, Immediate<SixBit>),
+    Sub(Register<Rd>, Register<Rs2>),
+    Xor(Register<Rd>, Register<Rs2>),
+    Or(Register<Rd>, Register<Rs2>),
+    And(Register<Rd>, Register<Rs2>),
+    J(Immediate<JumpOffset>),
+    Beqz(Register<Rs1>, Immediate<BranchOffset>),
+    Bnez(Register<Rs1>, Immediate<BranchOffset>),
+    Slli(Register<Rd>, Immediate<ShiftAmount>),
+    Lwsp(Register<Rd>, Immediate<StackWordOffset>),
+    Jr(Register<Rs1>),
+    Mv(Register<Rd>, Register<Rs2>),
+    Ebreak,
+    Jalr(Register<Rs1>),
+    Add(Register<Rd>, Register<Rs2>),
+    Swsp(Register<Rs2>, Immediate<StackWordOffset>),
+}
+
+#[derive(Debug)]
+pub enum CBSpecialization {
+    Branch,
+    Arithmetic,
+}
+
+/* Named as in the RISC-V specification. */
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub enum Format {
+    CR,
+    CI,
+    CSS,
+    CIW,
+    CL,
+    CS,
+    CA,
+    CB(CBSpecialization),
+    CJ,
+}
+
+const STACK_POINTER: u32 = 2;
+
+fn check_compressed<R>(index: u32, reg: Register<R>) -> Result<Register<R>, Error> {
+    if reg.to_u32() >= 8 && reg.to_u32() < 16 {
+        Ok(reg)
+    } else {
+        Err(Error::InvalidArgument(
+            index,
+            InvalidArgument::Register(GetRegisterError::OutsideRange {
+                prefix: "x",
+                actual: reg.to_u32() as i32,
+                min: 8,
+                max: 15,
+            }),
+        ))
+    }
+}
+
+fn check_stack_pointer<R>(index: u32, reg: Register<R>) -> Result<(), Error> {
+    if reg.to_u32() == STACK_POINTER {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument(
+            index,
+            InvalidArgument::Register(GetRegisterError::OutsideRange {
+                prefix: "x",
+                actual: reg.to_u32() as i32,
+                min: STACK_POINTER as i32,
+                max: STACK_POINTER as i32,
+            }),
+        ))
+    }
+}
+
+fn check_not<R>(index: u32, reg: Register<R>, reserved: &[u32]) -> Result<Register<R>, Error> {
+    if reserved.contains(&reg.to_u32()) {
+        Err(Error::InvalidArgument(
+            index,
+            InvalidArgument::RegisterNotAllowed(reg.to_u32()),
+        ))
+    } else {
+        Ok(reg)
+    }
+}
+
+fn compressed_register<R>(index: u32, string: &str) -> Result<Register<R>, Error> {
+    parse_register(index, string).and_then(|reg| check_compressed(index, reg))
+}
+
+fn non_zero_register<R>(index: u32, string: &str) -> Result<Register<R>, Error> {
+    parse_register(index, string).and_then(|reg| check_not(index, reg, &[0]))
+}
+
+fn compressed_memory_argument(
+    index: u32,
+    string: &str,
+) -> Result<(Register<Rs1>, Immediate<WordOffset>), Error> {
+    let (rs1, offset) =
+        get_memory_argument(string).map_err(|e| Error::InvalidArgument(index, e))?;
+    Ok((check_compressed(index, rs1)?, offset))
+}
+
+fn stack_memory_argument(index: u32, string: &str) -> Result<Immediate<StackWordOffset>, Error> {
+    let (rs1, offset) =
+        get_memory_argument(string).map_err(|e| Error::InvalidArgument(index, e))?;
+    check_stack_pointer(index, rs1)?;
+    Ok(offset)
+}
+
+type RegisterPair = (Register<Rd>, Register<Rs2>);
+
+fn parse_compressed_pair(args: &[&str]) -> Result<RegisterPair, Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            Some(|rd, rs2| Ok((compressed_register(0, rd)?, compressed_register(1, rs2)?))),
+            None,
+        ),
+    )
+}
+
+fn parse_register_pair(args: &[&str]) -> Result<RegisterPair, Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            Some(|rd, rs2| Ok((non_zero_register(0, rd)?, non_zero_register(1, rs2)?))),
+            None,
+        ),
+    )
+}
+
+fn parse_jump(args: &[&str]) -> Result<Immediate<JumpOffset>, Error> {
+    parse_help(
+        args,
+        (None, Some(|imm| parse_immediate(0, imm)), None, None),
+    )
+}
+
+fn parse_jump_register(args: &[&str]) -> Result<Register<Rs1>, Error> {
+    parse_help(
+        args,
+        (None, Some(|rs1| non_zero_register(0, rs1)), None, None),
+    )
+}
+
+fn parse_branch(args: &[&str]) -> Result<(Register<Rs1>, Immediate<BranchOffset>), Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            Some(|rs1, imm| Ok((compressed_register(0, rs1)?, parse_immediate(1, imm)?))),
+            None,
+        ),
+    )
+}
+
+fn parse_compressed_shift(args: &[&str]) -> Result<(Register<Rd>, Immediate<ShiftAmount>), Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            Some(|rd, shamt| Ok((compressed_register(0, rd)?, parse_immediate(1, shamt)?))),
+            None,
+        ),
+    )
+}
+
+/* The names of every instruction that `CompressedInstruction::from_str`
+ * accepts.
+ */
+pub const MNEMONICS: &[&str] = &[
+    "c.addi4spn",
+    "c.lw",
+    "c.sw",
+    "c.nop",
+    "c.addi",
+    "c.jal",
+    "c.li",
+    "c.addi16sp",
+    "c.lui",
+    "c.srli",
+    "c.srai",
+    "c.andi",
+    "c.sub",
+    "c.xor",
+    "c.or",
+    "c.and",
+    "c.j",
+    "c.beqz",
+    "c.bnez",
+    "c.slli",
+    "c.lwsp",
+    "c.jr",
+    "c.mv",
+    "c.ebreak",
+    "c.jalr",
+    "c.add",
+    "c.swsp",
+];
+
+impl FromStr for CompressedInstruction {
+    type Err = Error;
+
+    fn from_str(mnemonic: &str) -> Result<Self, Error> {
+        let (name, args) = split_mnemonic(mnemonic);
+
+        match name.to_ascii_lowercase().as_str() {
+            "c.addi4spn" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    None,
+                    Some(|rd, sp, imm| {
+                        let rd = compressed_register(0, rd)?;
+                        check_stack_pointer(1, parse_register::<Rs1>(1, sp)?)?;
+                        Ok(CompressedInstruction::Addi4spn(
+                            rd,
+                            parse_immediate(2, imm)?,
+                        ))
+                    }),
+                ),
+            ),
+            "c.lw" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|rd, mem_arg| {
+                        let rd = compressed_register(0, rd)?;
+                        let (rs1, offset) = compressed_memory_argument(1, mem_arg)?;
+                        Ok(CompressedInstruction::Lw(rd, rs1, offset))
+                    }),
+                    None,
+                ),
+            ),
+            "c.sw" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|rs2, mem_arg| {
+                        let rs2 = compressed_register(0, rs2)?;
+                        let (rs1, offset) = compressed_memory_argument(1, mem_arg)?;
+                        Ok(CompressedInstruction::Sw(rs2, rs1, offset))
+                    }),
+                    None,
+                ),
+            ),
+            "c.nop" => parse_help(
+                &args,
+                (Some(|| Ok(CompressedInstruction::Nop)), None, None, None),
+            ),
+            "c.addi" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|rd, imm| {
+                        Ok(CompressedInstruction::Addi(
+                            non_zero_register(0, rd)?,
+                            parse_immediate(1, imm)?,
+                        ))
+                    }),
+                    None,
+                ),
+            ),
+            "c.jal" => parse_jump(&args).map(CompressedInstruction::Jal),
+            "c.li" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|rd, imm| {
+                        Ok(CompressedInstruction::Li(
+                            non_zero_register(0, rd)?,
+                            parse_immediate(1, imm)?,
+                        ))
+                    }),
+                    None,
+                ),
+            ),
+            "c.addi16sp" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|sp, imm| {
+                        check_stack_pointer(0, parse_register::<Rd>(0, sp)?)?;
+                        Ok(CompressedInstruction::Addi16sp(parse_immediate(1, imm)?))
+                    }),
+                    None,
+                ),
+            ),
+            "c.lui" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|rd, imm| {
+                        let rd = check_not(0, parse_register(0, rd)?, &[0, STACK_POINTER])?;
+                        Ok(CompressedInstruction::Lui(rd, parse_immediate(1, imm)?))
+                    }),
+                    None,
+                ),
+            ),
+            "c.srli" => parse_compressed_shift(&args)
+                .map(|(rd, shamt)| CompressedInstruction::Srli(rd, shamt)),
+            "c.srai" => parse_compressed_shift(&args)
+                .map(|(rd, shamt)| CompressedInstruction::Srai(rd, shamt)),
+            "c.andi" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|rd, imm| {
+                        Ok(CompressedInstruction::Andi(
+                            compressed_register(0, rd)?,
+                            parse_immediate(1, imm)?,
+                        ))
+                    }),
+                    None,
+                ),
+            ),
+            "c.sub" => {
+                parse_compressed_pair(&args).map(|(rd, rs2)| CompressedInstruction::Sub(rd, rs2))
+            }
+            "c.xor" => {
+                parse_compressed_pair(&args).map(|(rd, rs2)| CompressedInstruction::Xor(rd, rs2))
+            }
+            "c.or" => {
+                parse_compressed_pair(&args).map(|(rd, rs2)| CompressedInstruction::Or(rd, rs2))
+            }
+            "c.and" => {
+                parse_compressed_pair(&args).map(|(rd, rs2)| CompressedInstruction::And(rd, rs2))
+            }
+            "c.j" => parse_jump(&args).map(CompressedInstruction::J),
+            "c.beqz" => parse_branch(&args).map(|(rs1, imm)| CompressedInstruction::Beqz(rs1, imm)),
+            "c.bnez" => parse_branch(&args).map(|(rs1, imm)| CompressedInstruction::Bnez(rs1, imm)),
+            "c.slli" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|rd, shamt| {
+                        Ok(CompressedInstruction::Slli(
+                            non_zero_register(0, rd)?,
+                            parse_immediate(1, shamt)?,
+                        ))
+                    }),
+                    None,
+                ),
+            ),
+            "c.lwsp" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|rd, mem_arg| {
+                        Ok(CompressedInstruction::Lwsp(
+                            non_zero_register(0, rd)?,
+                            stack_memory_argument(1, mem_arg)?,
+                        ))
+                    }),
+                    None,
+                ),
+            ),
+            "c.jr" => parse_jump_register(&args).map(CompressedInstruction::Jr),
+            "c.mv" => {
+                parse_register_pair(&args).map(|(rd, rs2)| CompressedInstruction::Mv(rd, rs2))
+            }
+            "c.ebreak" => parse_help(
+                &args,
+                (Some(|| Ok(CompressedInstruction::Ebreak)), None, None, None),
+            ),
+            "c.jalr" => parse_jump_register(&args).map(CompressedInstruction::Jalr),
+            "c.add" => {
+                parse_register_pair(&args).map(|(rd, rs2)| CompressedInstruction::Add(rd, rs2))
+            }
+            "c.swsp" => parse_help(
+                &args,
+                (
+                    None,
+                    None,
+                    Some(|rs2, mem_arg| {
+                        Ok(CompressedInstruction::Swsp(
+                            parse_register(0, rs2)?,
+                            stack_memory_argument(1, mem_arg)?,
+                        ))
+                    }),
+                    None,
+                ),
+            ),
+            _ => Err(invalid_instruction_name(name, MNEMONICS.iter().cloned())),
+        }
+    }
+}
+
+impl fmt::Display for CompressedInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompressedInstruction::Addi4spn(rd, imm) => {
+                write!(f, "c.addi4spn {},x2,{}", rd, imm)
+            }
+            CompressedInstruction::Lw(rd, rs1, imm) => write!(f, "c.lw {},{}({})", rd, imm, rs1),
+            CompressedInstruction::Sw(rs2, rs1, imm) => {
+                write!(f, "c.sw {},{}({})", rs2, imm, rs1)
+            }
+            CompressedInstruction::Nop => write!(f, "c.nop"),
+            CompressedInstruction::Addi(rd, imm) => write!(f, "c.addi {},{}", rd, imm),
+            CompressedInstruction::Jal(imm) => write!(f, "c.jal {}", imm),
+            CompressedInstruction::Li(rd, imm) => write!(f, "c.li {},{}", rd, imm),
+            CompressedInstruction::Addi16sp(imm) => write!(f, "c.addi16sp x2,{}", imm),
+            CompressedInstruction::Lui(rd, imm) => write!(f, "c.lui {},{}", rd, imm),
+            CompressedInstruction::Srli(rd, shamt) => write!(f, "c.srli {},{}", rd, shamt),
+            CompressedInstruction::Srai(rd, shamt) => write!(f, "c.srai {},{}", rd, shamt),
+            CompressedInstruction::Andi(rd, imm) => write!(f, "c.andi {},{}", rd, imm),
+            CompressedInstruction::Sub(rd, rs2) => write!(f, "c.sub {},{}", rd, rs2),
+            CompressedInstruction::Xor(rd, rs2) => write!(f, "c.xor {},{}", rd, rs2),
+            CompressedInstruction::Or(rd, rs2) => write!(f, "c.or {},{}", rd, rs2),
+            CompressedInstruction::And(rd, rs2) => write!(f, "c.and {},{}", rd, rs2),
+            CompressedInstruction::J(imm) => write!(f, "c.j {}", imm),
+            CompressedInstruction::Beqz(rs1, imm) => write!(f, "c.beqz {},{}", rs1, imm),
+            CompressedInstruction::Bnez(rs1, imm) => write!(f, "c.bnez {},{}", rs1, imm),
+            CompressedInstruction::Slli(rd, shamt) => write!(f, "c.slli {},{}", rd, shamt),
+            CompressedInstruction::Lwsp(rd, imm) => write!(f, "c.lwsp {},{}(x2)", rd, imm),
+            CompressedInstruction::Jr(rs1) => write!(f, "c.jr {}", rs1),
+            CompressedInstruction::Mv(rd, rs2) => write!(f, "c.mv {},{}", rd, rs2),
+            CompressedInstruction::Ebreak => write!(f, "c.ebreak"),
+            CompressedInstruction::Jalr(rs1) => write!(f, "c.jalr {}", rs1),
+            CompressedInstruction::Add(rd, rs2) => write!(f, "c.add {},{}", rd, rs2),
+            CompressedInstruction::Swsp(rs2, imm) => write!(f, "c.swsp {},{}(x2)", rs2, imm),
+        }
+    }
+}
+
+/* value[hi:lo] shifted down to bit 0.
+ */
+fn bits<X: Constraints>(value: &Immediate<X>, hi: u32, lo: u32) -> u16 {
+    ((value.to_i32() as u32 >> lo) & ((1 << (hi - lo + 1)) - 1)) as u16
+}
+
+/* The three bit register field used by the CIW, CL, CS, CA and CB formats.
+ */
+fn compressed_field<R>(reg: &Register<R>) -> u16 {
+    (reg.to_u32() - 8) as u16
+}
+
+fn full_field<R>(reg: &Register<R>) -> u16 {
+    reg.to_u32() as u16
+}
+
+fn ci<X: Constraints>(funct3: u16, rd: u16, imm: &Immediate<X>, op: u16) -> u16 {
+    (funct3 << 13) | (bits(imm, 5, 5) << 12) | (rd << 7) | (bits(imm, 4, 0) << 2) | op
+}
+
+fn cj(funct3: u16, imm: &Immediate<JumpOffset>) -> u16 {
+    // imm[11|4|9:8|10|6|7|3:1|5] -> inst[12:2]
+    (funct3 << 13)
+        | (bits(imm, 11, 11) << 12)
+        | (bits(imm, 4, 4) << 11)
+        | (bits(imm, 9, 8) << 9)
+        | (bits(imm, 10, 10) << 8)
+        | (bits(imm, 6, 6) << 7)
+        | (bits(imm, 7, 7) << 6)
+        | (bits(imm, 3, 1) << 3)
+        | (bits(imm, 5, 5) << 2)
+        | 0b01
+}
+
+fn cb(funct3: u16, rs1: &Register<Rs1>, imm: &Immediate<BranchOffset>) -> u16 {
+    // imm[8|4:3] -> inst[12:10]
+    // imm[7:6|2:1|5] -> inst[6:2]
+    (funct3 << 13)
+        | (bits(imm, 8, 8) << 12)
+        | (bits(imm, 4, 3) << 10)
+        | (compressed_field(rs1) << 7)
+        | (bits(imm, 7, 6) << 5)
+        | (bits(imm, 2, 1) << 3)
+        | (bits(imm, 5, 5) << 2)
+        | 0b01
+}
+
+fn cb_arithmetic<X: Constraints>(funct2: u16, rd: &Register<Rd>, imm: &Immediate<X>) -> u16 {
+    (0b100 << 13)
+        | (bits(imm, 5, 5) << 12)
+        | (funct2 << 10)
+        | (compressed_field(rd) << 7)
+        | (bits(imm, 4, 0) << 2)
+        | 0b01
+}
+
+fn ca(funct2: u16, rd: &Register<Rd>, rs2: &Register<Rs2>) -> u16 {
+    (0b100011 << 10)
+        | (compressed_field(rd) << 7)
+        | (funct2 << 5)
+        | (compressed_field(rs2) << 2)
+        | 0b01
+}
+
+fn cr(funct4: u16, rd: u16, rs2: u16) -> u16 {
+    (funct4 << 12) | (rd << 7) | (rs2 << 2) | 0b10
+}
+
+fn cl_offset(imm: &Immediate<WordOffset>) -> u16 {
+    // imm[5:3] -> inst[12:10]
+    // imm[2|6] -> inst[6:5]
+    (bits(imm, 5, 3) << 10) | (bits(imm, 2, 2) << 6) | (bits(imm, 6, 6) << 5)
+}
+
+impl CompressedInstruction {
+    pub fn to_u16(&self) -> u16 {
+        match self {
+            CompressedInstruction::Addi4spn(rd, imm) => {
+                // imm[5:4|9:6|2|3] -> inst[12:5]
+                (bits(imm, 5, 4) << 11)
+                    | (bits(imm, 9, 6) << 7)
+                    | (bits(imm, 2, 2) << 6)
+                    | (bits(imm, 3, 3) << 5)
+                    | (compressed_field(rd) << 2)
+            }
+            CompressedInstruction::Lw(rd, rs1, imm) => {
+                (0b010 << 13)
+                    | cl_offset(imm)
+                    | (compressed_field(rs1) << 7)
+                    | (compressed_field(rd) << 2)
+            }
+            CompressedInstruction::Sw(rs2, rs1, imm) => {
+                (0b110 << 13)
+                    | cl_offset(imm)
+                    | (compressed_field(rs1) << 7)
+                    | (compressed_field(rs2) << 2)
+            }
+            CompressedInstruction::Nop => 0b01,
+            CompressedInstruction::Addi(rd, imm) => ci(0b000, full_field(rd), imm, 0b01),
+            CompressedInstruction::Jal(imm) => cj(0b001, imm),
+            CompressedInstruction::Li(rd, imm) => ci(0b010, full_field(rd), imm, 0b01),
+            CompressedInstruction::Addi16sp(imm) => {
+                // imm[9] -> inst[12]
+                // imm[4|6|8:7|5] -> inst[6:2]
+                (0b011 << 13)
+                    | (bits(imm, 9, 9) << 12)
+                    | ((STACK_POINTER as u16) << 7)
+                    | (bits(imm, 4, 4) << 6)
+                    | (bits(imm, 6, 6) << 5)
+                    | (bits(imm, 8, 7) << 3)
+                    | (bits(imm, 5, 5) << 2)
+                    | 0b01
+            }
+            CompressedInstruction::Lui(rd, imm) => ci(0b011, full_field(rd), imm, 0b01),
+            CompressedInstruction::Srli(rd, shamt) => cb_arithmetic(0b00, rd, shamt),
+            CompressedInstruction::Srai(rd, shamt) => cb_arithmetic(0b01, rd, shamt),
+            CompressedInstruction::Andi(rd, imm) => cb_arithmetic(0b10, rd, imm),
+            CompressedInstruction::Sub(rd, rs2) => ca(0b00, rd, rs2),
+            CompressedInstruction::Xor(rd, rs2) => ca(0b01, rd, rs2),
+            CompressedInstruction::Or(rd, rs2) => ca(0b10, rd, rs2),
+            CompressedInstruction::And(rd, rs2) => ca(0b11, rd, rs2),
+            CompressedInstruction::J(imm) => cj(0b101, imm),
+            CompressedInstruction::Beqz(rs1, imm) => cb(0b110, rs1, imm),
+            CompressedInstruction::Bnez(rs1, imm) => cb(0b111, rs1, imm),
+            CompressedInstruction::Slli(rd, shamt) => ci(0b000, full_field(rd), shamt, 0b10),
+            CompressedInstruction::Lwsp(rd, imm) => {
+                // imm[5] -> inst[12]
+                // imm[4:2|7:6] -> inst[6:2]
+                (0b010 << 13)
+                    | (bits(imm, 5, 5) << 12)
+                    | (full_field(rd) << 7)
+                    | (bits(imm, 4, 2) << 4)
+                    | (bits(imm, 7, 6) << 2)
+                    | 0b10
+            }
+            CompressedInstruction::Jr(rs1) => cr(0b1000, full_field(rs1), 0),
+            CompressedInstruction::Mv(rd, rs2) => cr(0b1000, full_field(rd), full_field(rs2)),
+            CompressedInstruction::Ebreak => cr(0b1001, 0, 0),
+            CompressedInstruction::Jalr(rs1) => cr(0b1001, full_field(rs1), 0),
+            CompressedInstruction::Add(rd, rs2) => cr(0b1001, full_field(rd), full_field(rs2)),
+            CompressedInstruction::Swsp(rs2, imm) => {
+                // imm[5:2|7:6] -> inst[12:7]
+                (0b110 << 13)
+                    | (bits(imm, 5, 2) << 9)
+                    | (bits(imm, 7, 6) << 7)
+                    | (full_field(rs2) << 2)
+                    | 0b10
+            }
+        }
+    }
+
+    pub fn to_format(&self) -> Format {
+        match self {
+            CompressedInstruction::Addi4spn(..) => Format::CIW,
+            CompressedInstruction::Lw(..) => Format::CL,
+            CompressedInstruction::Sw(..) => Format::CS,
+            CompressedInstruction::Nop => Format::CI,
+            CompressedInstruction::Addi(..) => Format::CI,
+            CompressedInstruction::Jal(_) => Format::CJ,
+            CompressedInstruction::Li(..) => Format::CI,
+            CompressedInstruction::Addi16sp(_) => Format::CI,
+            CompressedInstruction::Lui(..) => Format::CI,
+            CompressedInstruction::Srli(..) => Format::CB(CBSpecialization::Arithmetic),
+            CompressedInstruction::Srai(..) => Format::CB(CBSpecialization::Arithmetic),
+            CompressedInstruction::Andi(..) => Format::CB(CBSpecialization::Arithmetic),
+            CompressedInstruction::Sub(..) => Format::CA,
+            CompressedInstruction::Xor(..) => Format::CA,
+            CompressedInstruction::Or(..) => Format::CA,
+            CompressedInstruction::And(..) => Format::CA,
+            CompressedInstruction::J(_) => Format::CJ,
+            CompressedInstruction::Beqz(..) => Format::CB(CBSpecialization::Branch),
+            CompressedInstruction::Bnez(..) => Format::CB(CBSpecialization::Branch),
+            CompressedInstruction::Slli(..) => Format::CI,
+            CompressedInstruction::Lwsp(..) => Format::CI,
+            CompressedInstruction::Jr(_) => Format::CR,
+            CompressedInstruction::Mv(..) => Format::CR,
+            CompressedInstruction::Ebreak => Format::CR,
+            CompressedInstruction::Jalr(_) => Format::CR,
+            CompressedInstruction::Add(..) => Format::CR,
+            CompressedInstruction::Swsp(..) => Format::CSS,
+        }
+    }
+
+    /* The 32 bit instruction that this compressed instruction is shorthand
+     * for. narvie only accepts 32 bit instructions over UART so compressed
+     * instructions are evaluated by sending their expansion.
+     */
+    pub fn expand(&self) -> Instruction {
+        fn imm<X: Constraints, Y: Constraints>(imm: &Immediate<X>) -> Immediate<Y> {
+            Immediate::from_i32(imm.to_i32()).unwrap()
+        }
+        fn sp<R>() -> Register<R> {
+            Register::from_u32(STACK_POINTER).unwrap()
+        }
+        fn ra() -> Register<Rd> {
+            Register::from_u32(1).unwrap()
+        }
+
+        match self {
+            CompressedInstruction::Addi4spn(rd, nzuimm) => {
+                Instruction::Addi(I::new(rd.to_role(), sp(), imm(nzuimm)))
+            }
+            CompressedInstruction::Lw(rd, rs1, offset) => {
+                Instruction::Lw(Load::new(rd.to_role(), rs1.to_role(), imm(offset)))
+            }
+            CompressedInstruction::Sw(rs2, rs1, offset) => {
+                Instruction::Sw(S::new(rs1.to_role(), rs2.to_role(), imm(offset)))
+            }
+            CompressedInstruction::Nop => Instruction::Addi(I::new(
+                Register::ZERO,
+                Register::ZERO,
+                Immediate::from_i32(0).unwrap(),
+            )),
+            CompressedInstruction::Addi(rd, nzimm) => {
+                Instruction::Addi(I::new(rd.to_role(), rd.to_role(), imm(nzimm)))
+            }
+            CompressedInstruction::Jal(offset) => Instruction::Jal(J::new(ra(), imm(offset))),
+            CompressedInstruction::Li(rd, value) => {
+                Instruction::Addi(I::new(rd.to_role(), Register::ZERO, imm(value)))
+            }
+            CompressedInstruction::Addi16sp(nzimm) => {
+                Instruction::Addi(I::new(sp(), sp(), imm(nzimm)))
+            }
+            CompressedInstruction::Lui(rd, nzimm) => Instruction::Lui(U::new(
+                rd.to_role(),
+                Immediate::from_i32(nzimm.to_i32() & 0xFFFFF).unwrap(),
+            )),
+            CompressedInstruction::Srli(rd, shamt) => {
+                Instruction::Srli(Shift::new(rd.to_role(), rd.to_role(), imm(shamt)))
+            }
+            CompressedInstruction::Srai(rd, shamt) => {
+                Instruction::Srai(Shift::new(rd.to_role(), rd.to_role(), imm(shamt)))
+            }
+            CompressedInstruction::Andi(rd, value) => {
+                Instruction::Andi(I::new(rd.to_role(), rd.to_role(), imm(value)))
+            }
+            CompressedInstruction::Sub(rd, rs2) => {
+                Instruction::Sub(R::new(rd.to_role(), rd.to_role(), rs2.to_role()))
+            }
+            CompressedInstruction::Xor(rd, rs2) => {
+                Instruction::Xor(R::new(rd.to_role(), rd.to_role(), rs2.to_role()))
+            }
+            CompressedInstruction::Or(rd, rs2) => {
+                Instruction::Or(R::new(rd.to_role(), rd.to_role(), rs2.to_role()))
+            }
+            CompressedInstruction::And(rd, rs2) => {
+                Instruction::And(R::new(rd.to_role(), rd.to_role(), rs2.to_role()))
+            }
+            CompressedInstruction::J(offset) => {
+                Instruction::Jal(J::new(Register::ZERO, imm(offset)))
+            }
+            CompressedInstruction::Beqz(rs1, offset) => {
+                Instruction::Beq(B::new(rs1.to_role(), Register::ZERO, imm(offset)))
+            }
+            CompressedInstruction::Bnez(rs1, offset) => {
+                Instruction::Bne(B::new(rs1.to_role(), Register::ZERO, imm(offset)))
+            }
+            CompressedInstruction::Slli(rd, shamt) => {
+                Instruction::Slli(Shift::new(rd.to_role(), rd.to_role(), imm(shamt)))
+            }
+            CompressedInstruction::Lwsp(rd, offset) => {
+                Instruction::Lw(Load::new(rd.to_role(), sp(), imm(offset)))
+            }
+            CompressedInstruction::Jr(rs1) => Instruction::Jalr(I::new(
+                Register::ZERO,
+                rs1.to_role(),
+                Immediate::from_i32(0).unwrap(),
+            )),
+            CompressedInstruction::Mv(rd, rs2) => {
+                Instruction::Add(R::new(rd.to_role(), Register::ZERO, rs2.to_role()))
+            }
+            CompressedInstruction::Ebreak => Instruction::Ebreak(I::new(
+                Register::ZERO,
+                Register::ZERO,
+                Immediate::from_i32(0).unwrap(),
+            )),
+            CompressedInstruction::Jalr(rs1) => {
+                Instruction::Jalr(I::new(ra(), rs1.to_role(), Immediate::from_i32(0).unwrap()))
+            }
+            CompressedInstruction::Add(rd, rs2) => {
+                Instruction::Add(R::new(rd.to_role(), rd.to_role(), rs2.to_role()))
+            }
+            CompressedInstruction::Swsp(rs2, offset) => {
+                Instruction::Sw(S::new(sp(), rs2.to_role(), imm(offset)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_expand() {
+        let expected = [
+            ("c.addi4spn s0, sp, 16", 0x0800, "addi s0, sp, 16"),
+            ("c.lw a0, 0(a0)", 0x4108, "lw a0, 0(a0)"),
+            ("c.sw a0, (a1)", 0xC188, "sw a0, 0(a1)"),
+            ("c.nop", 0x0001, "addi zero, zero, 0"),
+            ("c.addi a0, 1", 0x0505, "addi a0, a0, 1"),
+            ("c.jal 0", 0x2001, "jal ra, 0"),
+            ("c.li a0, 1", 0x4505, "addi a0, zero, 1"),
+            ("c.addi16sp sp, -48", 0x7179, "addi sp, sp, -48"),
+            ("c.lui a0, 1", 0x6505, "lui a0, 1"),
+            ("c.lui a0, -1", 0x757D, "lui a0, 0xFFFFF"),
+            ("c.srli a0, 1", 0x8105, "srli a0, a0, 1"),
+            ("c.srai a0, 1", 0x8505, "srai a0, a0, 1"),
+            ("c.andi a0, 1", 0x8905, "andi a0, a0, 1"),
+            ("c.sub a0, a1", 0x8D0D, "sub a0, a0, a1"),
+            ("c.xor a0, a1", 0x8D2D, "xor a0, a0, a1"),
+            ("c.or a0, a1", 0x8D4D, "or a0, a0, a1"),
+            ("c.and a0, a1", 0x8D6D, "and a0, a0, a1"),
+            ("c.j 0", 0xA001, "jal zero, 0"),
+            ("c.beqz a0, 0", 0xC101, "beq a0, zero, 0"),
+            ("c.bnez a5, -2", 0xFFFD, "bne a5, zero, -2"),
+            ("c.slli a0, 2", 0x050A, "slli a0, a0, 2"),
+            ("c.lwsp ra, 12(sp)", 0x40B2, "lw ra, 12(sp)"),
+            ("c.jr ra", 0x8082, "jalr zero, ra, 0"),
+            ("c.mv a0, a1", 0x852E, "add a0, zero, a1"),
+            ("c.ebreak", 0x9002, "ebreak"),
+            ("c.jalr a0", 0x9502, "jalr ra, a0, 0"),
+            ("c.add a0, a1", 0x952E, "add a0, a0, a1"),
+            ("c.swsp ra, 12(sp)", 0xC606, "sw ra, 12(sp)"),
+        ];
+
+        for (mnemonic, halfword, expansion) in expected.iter() {
+            let compressed = CompressedInstruction::from_str(mnemonic).unwrap();
+            assert_eq!(compressed.to_u16(), *halfword, "{}", mnemonic);
+            assert_eq!(
+                compressed.expand().to_u32(),
+                Instruction::from_str(expansion).unwrap().to_u32(),
+                "{}",
+                mnemonic
+            );
+            assert_eq!(
+                CompressedInstruction::from_str(&compressed.to_string())
+                    .unwrap()
+                    .to_u16(),
+                *halfword,
+                "{}",
+                compressed
+            );
+        }
+    }
+
+    #[test]
+    fn reject_invalid_operands() {
+        let invalid = [
+            "c.lw a0, 0(sp)",
+            "c.lw a0, 2(a1)",
+            "c.addi4spn a0, sp, 0",
+            "c.addi4spn a0, a1, 4",
+            "c.addi a0, 0",
+            "c.addi16sp sp, 8",
+            "c.lui sp, 1",
+            "c.lui a0, 32",
+            "c.srli a0, 0",
+            "c.sub a0, t0",
+            "c.jr zero",
+            "c.mv a0, zero",
+            "c.lwsp a0, 4(a0)",
+            "c.beqz a0, 256",
+            "c.j 1",
+        ];
+
+        for mnemonic in invalid.iter() {
+            assert!(
+                CompressedInstruction::from_str(mnemonic).is_err(),
+                "{}",
+                mnemonic
+            );
+        }
+    }
+}
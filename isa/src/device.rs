@@ -0,0 +1,167 @@
+/* The protocol for talking to narvie. Each device sends an instruction to
+ * narvie and reads back the register file, so that the protocol lives in one
+ * place.
+ */
+
+use crate::register_file::RegisterFile;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+#[derive(Debug)]
+pub enum DeviceError {
+    Write(io::Error),
+    Read(io::Error),
+}
+
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceError::Write(e) => write!(f, "cannot send instruction to narvie: {}", e),
+            DeviceError::Read(e) => write!(f, "cannot read registers from narvie: {}", e),
+        }
+    }
+}
+
+impl Error for DeviceError {}
+
+pub trait NarvieDevice {
+    /* Evaluates one instruction and returns the register file after it.
+     */
+    fn execute(&mut self, instruction: u32) -> Result<RegisterFile, DeviceError>;
+
+    /* Puts narvie back in the state it started in, with every register, CSR
+     * and word of memory zero. Returns false if the device cannot be reset,
+     * such as narvie on an FPGA.
+     */
+    fn reset(&mut self) -> Result<bool, DeviceError> {
+        Ok(false)
+    }
+}
+
+impl<D: NarvieDevice + ?Sized> NarvieDevice for Box<D> {
+    fn execute(&mut self, instruction: u32) -> Result<RegisterFile, DeviceError> {
+        (**self).execute(instruction)
+    }
+
+    fn reset(&mut self) -> Result<bool, DeviceError> {
+        (**self).reset()
+    }
+}
+
+/* narvie at the other end of a stream of bytes. Each instruction is sent as 4
+ * little endian bytes and the 32 registers are sent back as 128 little endian
+ * bytes.
+ */
+pub struct StreamDevice<S> {
+    stream: S,
+}
+
+impl<S: Read + Write> StreamDevice<S> {
+    pub fn new(stream: S) -> Self {
+        StreamDevice { stream }
+    }
+}
+
+impl<S: Read + Write> NarvieDevice for StreamDevice<S> {
+    fn execute(&mut self, instruction: u32) -> Result<RegisterFile, DeviceError> {
+        self.stream
+            .write_all(&instruction.to_le_bytes())
+            .map_err(DeviceError::Write)?;
+
+        let mut buf = [0; RegisterFile::BYTES];
+        self.stream
+            .read_exact(&mut buf)
+            .map_err(DeviceError::Read)?;
+        Ok(RegisterFile::from_le_bytes(&buf))
+    }
+}
+
+/* A simulation of narvie served over tcp by `narvie-processor`.
+ */
+pub type TcpDevice = StreamDevice<TcpStream>;
+
+pub fn tcp<A: ToSocketAddrs>(address: A) -> io::Result<TcpDevice> {
+    TcpStream::connect(address).map(StreamDevice::new)
+}
+
+/* Replies with the register file returned by `respond`, remembering every
+ * instruction it was sent. For testing code that drives narvie.
+ */
+pub struct MockDevice<F> {
+    respond: F,
+    pub received: Vec<u32>,
+}
+
+impl<F: FnMut(u32) -> RegisterFile> MockDevice<F> {
+    pub fn new(respond: F) -> Self {
+        MockDevice {
+            respond,
+            received: Vec::new(),
+        }
+    }
+}
+
+impl<F: FnMut(u32) -> RegisterFile> NarvieDevice for MockDevice<F> {
+    fn execute(&mut self, instruction: u32) -> Result<RegisterFile, DeviceError> {
+        self.received.push(instruction);
+        Ok((self.respond)(instruction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Replies with the bytes of a register file where register `i` holds
+     * `3 * i`, remembering the bytes sent to it.
+     */
+    struct Loopback {
+        replies: io::Cursor<Vec<u8>>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.sent.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stream_protocol() {
+        let replies = (0..32u32)
+            .flat_map(|i| (i * 3).to_le_bytes().to_vec())
+            .collect();
+        let mut device = StreamDevice::new(Loopback {
+            replies: io::Cursor::new(replies),
+            sent: Vec::new(),
+        });
+
+        let reg_file = device.execute(0x0030_0513).unwrap();
+        assert_eq!(device.stream.sent, vec![0x13, 0x05, 0x30, 0x00]);
+        assert_eq!(reg_file["a0"], 30);
+
+        match device.execute(0x0030_0513) {
+            Err(DeviceError::Read(_)) => {}
+            other => panic!("expected a read error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mock_records_instructions() {
+        let mut device = MockDevice::new(|instruction| RegisterFile::new([instruction; 32]));
+        assert_eq!(device.execute(7).unwrap()["t6"], 7);
+        assert_eq!(device.received, vec![7]);
+    }
+}
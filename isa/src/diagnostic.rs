@@ -0,0 +1,112 @@
+use std::fmt;
+
+/* A range of byte offsets into the line that was parsed.
+ *
+ * An empty span (`start == end`) points between two characters, for example
+ * at the end of the line where an argument is missing.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    pub fn point(offset: usize) -> Self {
+        Span::new(offset, offset)
+    }
+
+    /* Moves a span found within `self` to be relative to the whole line.
+     */
+    pub fn narrow(&self, inner: Span) -> Span {
+        Span::new(self.start + inner.start, self.start + inner.end)
+    }
+}
+
+/* Prints the input followed by a caret underline beneath `span` and the
+ * message, for example
+ *
+ *   addi t0, t0, 5000
+ *                ^^^^ immediate 5000 exceeds 12-bit signed range [-2048, 2047]
+ */
+pub fn render(input: &str, span: Span, message: &dyn fmt::Display) -> String {
+    let column = |offset: usize| input[..offset.min(input.len())].chars().count();
+    let start = column(span.start);
+    let width = (column(span.end) - start).max(1);
+
+    format!(
+        "{}\n{}{} {}",
+        input,
+        " ".repeat(start),
+        "^".repeat(width),
+        message
+    )
+}
+
+/* The number of single character insertions, deletions and substitutions
+ * needed to turn `a` into `b`.
+ */
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/* The candidate closest to `word`, ignoring case, if it is close enough to
+ * be a likely typo. Ties go to candidates that `word` starts with, so that
+ * `addd` suggests `add` rather than `addi`.
+ */
+pub fn closest<'a, I>(word: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let word = word.to_ascii_lowercase();
+    let max_distance = (word.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&word, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, candidate)| (*distance, !word.starts_with(candidate)))
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_underlines_span() {
+        assert_eq!(
+            render("addi t0, t0, 5000", Span::new(13, 17), &"out of range"),
+            "addi t0, t0, 5000\n             ^^^^ out of range"
+        );
+        assert_eq!(
+            render("addi t0", Span::point(7), &"missing"),
+            "addi t0\n       ^ missing"
+        );
+    }
+
+    #[test]
+    fn closest_candidate() {
+        assert_eq!(edit_distance("addd", "add"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(closest("addd", vec!["sub", "addi", "add"]), Some("add"));
+        assert_eq!(closest("LW", vec!["sw", "lw"]), Some("lw"));
+        assert_eq!(closest("bogus", vec!["add", "sub"]), None);
+    }
+}
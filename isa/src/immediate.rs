@@ -11,6 +11,8 @@ where
     const MAX: i32;
     const MIN: i32;
     const EVEN: bool;
+    const MULTIPLE_OF: i32 = 1;
+    const NON_ZERO: bool = false;
 
     fn from_special_string(string: &str) -> Option<Immediate<Self>>;
     fn write_help(imm: &Immediate<Self>, fmt: &mut fmt::Formatter) -> fmt::Result;
@@ -35,6 +37,8 @@ pub struct ShiftAmount;
 pub struct CsrSpecifier;
 #[derive(Debug)]
 pub struct CsrImmediate;
+#[derive(Debug)]
+pub struct Word;
 
 impl Constraints for U {
     const MAX: i32 = (1 << 20) - 1;
@@ -108,33 +112,31 @@ impl Constraints for ShiftAmount {
     }
 }
 
+/* The CSRs that can be referred to by name.
+ */
+pub const CSR_NAMES: &[(&str, i32)] = &[
+    ("cycle", 0xC00),
+    ("time", 0xC01),
+    ("instret", 0xC02),
+    ("cycleh", 0xC80),
+    ("timeh", 0xC81),
+    ("instreth", 0xC82),
+];
+
 impl Constraints for CsrSpecifier {
     const MAX: i32 = (1 << 12) - 1;
     const MIN: i32 = 0;
     const EVEN: bool = false;
     fn from_special_string(string: &str) -> Option<Immediate<Self>> {
-        Some(
-            Immediate::from_i32(match string {
-                "cycle" => 0xC00,
-                "time" => 0xC01,
-                "instret" => 0xC02,
-                "cycleh" => 0xC80,
-                "timeh" => 0xC81,
-                "instreth" => 0xC82,
-                _ => return None,
-            })
-            .unwrap(),
-        )
+        CSR_NAMES
+            .iter()
+            .find(|(name, _)| *name == string)
+            .map(|(_, csr)| Immediate::from_i32(*csr).unwrap())
     }
     fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
-        match value.to_i32() {
-            0xC00 => write!(f, "{}", "cycle"),
-            0xC01 => write!(f, "{}", "time"),
-            0xC02 => write!(f, "{}", "instret"),
-            0xC80 => write!(f, "{}", "cycleh"),
-            0xC81 => write!(f, "{}", "timeh"),
-            0xC82 => write!(f, "{}", "instreth"),
-            other => write!(f, "0x{:X}", other),
+        match CSR_NAMES.iter().find(|(_, csr)| *csr == value.to_i32()) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => write!(f, "0x{:X}", value.to_i32()),
         }
     }
 }
@@ -151,11 +153,44 @@ impl Constraints for CsrImmediate {
     }
 }
 
+impl Constraints for Word {
+    const MAX: i32 = i32::MAX;
+    const MIN: i32 = i32::MIN;
+    const EVEN: bool = false;
+    fn from_special_string(string: &str) -> Option<Immediate<Self>> {
+        // Accept anything that fits in 32 bits, signed or unsigned, such as
+        // 0xFFFFFFFF or -2147483648.
+        let (negative, string) = match string.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, string),
+        };
+        let (radix, digits) = if let Some(hex) = string.strip_prefix("0x") {
+            (16, hex)
+        } else if let Some(bin) = string.strip_prefix("0b") {
+            (2, bin)
+        } else {
+            (10, string)
+        };
+        if digits.starts_with(&['-', '+'][..]) {
+            return None;
+        }
+        i64::from_str_radix(digits, radix)
+            .ok()
+            .map(|word| if negative { -word } else { word })
+            .filter(|word| *word >= i64::from(i32::MIN) && *word <= i64::from(u32::MAX))
+            .map(|word| Immediate(word as i32, PhantomData))
+    }
+    fn write_help(value: &Immediate<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:08X}", value.to_i32())
+    }
+}
+
 #[derive(Debug)]
 pub enum ConstraintViolation {
-    LargerThan(i32),
-    SmallerThan(i32),
+    OutsideRange { actual: i32, min: i32, max: i32 },
     EvenNumberRequired,
+    MultipleRequired(i32),
+    NonZeroRequired,
 }
 
 #[derive(Debug)]
@@ -164,15 +199,78 @@ pub enum InvalidImmediate {
     NumericValue(ConstraintViolation),
 }
 
+/* Describes ranges such as [-2048, 2047] as "12-bit signed range" when they
+ * are exactly the values that fit in a field of that width.
+ */
+pub fn describe_range(min: i32, max: i32) -> String {
+    let (min, max) = (i64::from(min), i64::from(max));
+    let width = |count: i64| {
+        if count.count_ones() == 1 {
+            Some(count.trailing_zeros())
+        } else {
+            None
+        }
+    };
+    let bits = if min == 0 {
+        width(max + 1).map(|bits| format!("{}-bit unsigned range", bits))
+    } else if min == -(max + 1) {
+        width(max - min + 1).map(|bits| format!("{}-bit signed range", bits))
+    } else {
+        None
+    };
+    format!(
+        "{} [{}, {}]",
+        bits.unwrap_or_else(|| "range".to_string()),
+        min,
+        max
+    )
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstraintViolation::OutsideRange { actual, min, max } => write!(
+                f,
+                "immediate {} {} {}",
+                actual,
+                if actual > max { "exceeds" } else { "is below" },
+                describe_range(*min, *max)
+            ),
+            ConstraintViolation::EvenNumberRequired => write!(f, "immediate must be even"),
+            ConstraintViolation::MultipleRequired(multiple) => {
+                write!(f, "immediate must be a multiple of {}", multiple)
+            }
+            ConstraintViolation::NonZeroRequired => write!(f, "immediate must not be zero"),
+        }
+    }
+}
+
+impl fmt::Display for InvalidImmediate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidImmediate::Literal(literal) => {
+                write!(f, "`{}` is not a valid immediate", literal)
+            }
+            InvalidImmediate::NumericValue(violation) => write!(f, "{}", violation),
+        }
+    }
+}
+
 fn constraint_violated<X: Constraints>(
     Immediate(imm, PhantomData): &Immediate<X>,
 ) -> Option<ConstraintViolation> {
-    if imm > &X::MAX {
-        Some(ConstraintViolation::LargerThan(X::MAX))
-    } else if imm < &X::MIN {
-        Some(ConstraintViolation::SmallerThan(X::MIN))
+    if imm > &X::MAX || imm < &X::MIN {
+        Some(ConstraintViolation::OutsideRange {
+            actual: *imm,
+            min: X::MIN,
+            max: X::MAX,
+        })
     } else if X::EVEN && (imm & 1 != 0) {
         Some(ConstraintViolation::EvenNumberRequired)
+    } else if imm % X::MULTIPLE_OF != 0 {
+        Some(ConstraintViolation::MultipleRequired(X::MULTIPLE_OF))
+    } else if X::NON_ZERO && *imm == 0 {
+        Some(ConstraintViolation::NonZeroRequired)
     } else {
         None
     }
@@ -197,21 +295,17 @@ fn get_immediate(string: &str) -> Option<i32> {
     if string.is_empty() {
         None
     } else {
-        let (first, rest) = string.split_at(1);
-        if first == "-" {
+        let first = string.chars().next().unwrap();
+        let rest = &string[first.len_utf8()..];
+        if first == '-' {
             get_immediate(rest).map(|x| -x)
         } else if rest.is_empty() {
-            i32::from_str(first).ok()
+            first.to_digit(10).map(|digit| digit as i32)
         } else {
-            (if first == "0" {
-                let (specifier, numeric) = rest.split_at(1);
-                if specifier == "x" {
-                    Some((16, numeric))
-                } else if specifier == "b" {
-                    Some((2, numeric))
-                } else {
-                    None
-                }
+            (if first == '0' {
+                rest.strip_prefix('x')
+                    .map(|numeric| (16, numeric))
+                    .or_else(|| rest.strip_prefix('b').map(|numeric| (2, numeric)))
             } else {
                 Some((10, string))
             })
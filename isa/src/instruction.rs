@@ -0,0 +1,2804 @@
+use crate::diagnostic::{self, Span};
+use crate::immediate::{self, Immediate, InvalidImmediate};
+use crate::register::{GetRegisterError, Rd, Register, Rs1, Rs2};
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::string::String;
+
+#[derive(Debug)]
+pub enum GetMemoryLocationError {
+    MissingCloseParentheses,
+    TextAfterCloseParenthesis,
+}
+
+#[derive(Debug)]
+pub enum InvalidArgument {
+    Register(GetRegisterError),
+    Immediate(InvalidImmediate),
+    MemoryLocation(GetMemoryLocationError),
+    Fence(String),
+    RegisterNotAllowed(u32),
+}
+
+#[derive(Debug)]
+pub enum Error {
+    WrongNumberOfArgs {
+        actual: usize,
+        expected: Vec<usize>,
+    },
+    InvalidInstructionName {
+        name: String,
+        suggestion: Option<&'static str>,
+    },
+    InvalidArgument(u32, InvalidArgument),
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    IllegalOpcode(u32),
+    IllegalFunct3 {
+        opcode: u32,
+        funct3: u32,
+    },
+    IllegalFunct7 {
+        opcode: u32,
+        funct3: u32,
+        funct7: u32,
+    },
+    IllegalFunct12 {
+        funct12: u32,
+    },
+    ReservedBitsSet {
+        mask: u32,
+    },
+}
+
+impl fmt::Display for GetMemoryLocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GetMemoryLocationError::MissingCloseParentheses => write!(f, "missing `)`"),
+            GetMemoryLocationError::TextAfterCloseParenthesis => {
+                write!(f, "unexpected text after `)`")
+            }
+        }
+    }
+}
+
+impl fmt::Display for InvalidArgument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidArgument::Register(e) => write!(f, "{}", e),
+            InvalidArgument::Immediate(e) => write!(f, "{}", e),
+            InvalidArgument::MemoryLocation(e) => write!(f, "{}", e),
+            InvalidArgument::Fence(arg) => write!(
+                f,
+                "`{}` is not a fence ordering, expected a combination of i, o, r and w",
+                arg
+            ),
+            InvalidArgument::RegisterNotAllowed(reg) => {
+                write!(f, "register x{} cannot be used here", reg)
+            }
+        }
+    }
+}
+
+impl InvalidArgument {
+    /* Where in `argument` the problem lies, for memory locations such as
+     * `8(sp)` this is just the offset or the register.
+     */
+    fn span(&self, argument: &str) -> Span {
+        let whole = Span::new(0, argument.len());
+        let open = argument.find('(');
+        let close = argument.find(')');
+        match (self, open, close) {
+            (
+                InvalidArgument::MemoryLocation(GetMemoryLocationError::MissingCloseParentheses),
+                _,
+                _,
+            ) => Span::point(argument.len()),
+            (
+                InvalidArgument::MemoryLocation(GetMemoryLocationError::TextAfterCloseParenthesis),
+                _,
+                Some(close),
+            ) => Span::new(close + 1, argument.len()),
+            (InvalidArgument::Register(_), Some(open), Some(close)) if open < close => {
+                Span::new(open + 1, close)
+            }
+            (InvalidArgument::Immediate(_), Some(open), _) => Span::new(0, open),
+            _ => whole,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::WrongNumberOfArgs { actual, expected } => {
+                let expected: Vec<String> = expected.iter().map(usize::to_string).collect();
+                write!(
+                    f,
+                    "expected {} argument(s), found {}",
+                    expected.join(" or "),
+                    actual
+                )
+            }
+            Error::InvalidInstructionName {
+                name,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "unknown instruction `{}`, did you mean `{}`?",
+                name, suggestion
+            ),
+            Error::InvalidInstructionName {
+                name,
+                suggestion: None,
+            } => write!(f, "unknown instruction `{}`", name),
+            Error::InvalidArgument(_, e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error {
+    /* The part of `mnemonic`, the string that failed to parse, that the
+     * error refers to.
+     */
+    pub fn span(&self, mnemonic: &str) -> Span {
+        let (name, args) = split_mnemonic_spans(mnemonic);
+        match self {
+            Error::InvalidInstructionName { .. } => name,
+            Error::WrongNumberOfArgs { actual, expected } => {
+                let end = mnemonic.trim_end().len();
+                match expected.iter().max() {
+                    Some(max) if actual > max => Span::new(args[*max].start, end),
+                    _ => Span::point(end),
+                }
+            }
+            Error::InvalidArgument(index, e) => args
+                .get(*index as usize)
+                .map(|arg| arg.narrow(e.span(&mnemonic[arg.start..arg.end])))
+                .unwrap_or(name),
+        }
+    }
+
+    /* `mnemonic` with the offending part underlined, followed by a
+     * description of the error.
+     */
+    pub fn render(&self, mnemonic: &str) -> String {
+        diagnostic::render(mnemonic, self.span(mnemonic), self)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::IllegalOpcode(opcode) => write!(f, "illegal opcode 0b{:07b}", opcode),
+            DecodeError::IllegalFunct3 { opcode, funct3 } => write!(
+                f,
+                "illegal funct3 0b{:03b} for opcode 0b{:07b}",
+                funct3, opcode
+            ),
+            DecodeError::IllegalFunct7 {
+                opcode,
+                funct3,
+                funct7,
+            } => write!(
+                f,
+                "illegal funct7 0b{:07b} for opcode 0b{:07b} and funct3 0b{:03b}",
+                funct7, opcode, funct3
+            ),
+            DecodeError::IllegalFunct12 { funct12 } => {
+                write!(
+                    f,
+                    "illegal funct12 0x{:03X} for a system instruction",
+                    funct12
+                )
+            }
+            DecodeError::ReservedBitsSet { mask } => {
+                write!(f, "reserved bits 0x{:08X} must be zero", mask)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Opcode(u32);
+
+impl Opcode {
+    pub fn from_u32(opcode: u32) -> Option<Opcode> {
+        if opcode & !0x7F == 0 {
+            Some(Opcode(opcode))
+        } else {
+            None
+        }
+    }
+    pub fn to_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+struct Funct3(u32);
+
+impl Funct3 {
+    pub fn from_u32(funct3: u32) -> Option<Self> {
+        if funct3 & !0x7 == 0 {
+            Some(Funct3(funct3))
+        } else {
+            None
+        }
+    }
+    pub fn to_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+struct Funct7(u32);
+
+impl Funct7 {
+    pub fn from_u32(funct7: u32) -> Option<Self> {
+        if funct7 & !0x7F == 0 {
+            Some(Funct7(funct7))
+        } else {
+            None
+        }
+    }
+    pub fn to_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+struct Fm(u32);
+
+impl Fm {
+    pub fn from_u32(funct7: u32) -> Option<Self> {
+        if funct7 & !0xF == 0 {
+            Some(Self(funct7))
+        } else {
+            None
+        }
+    }
+    pub fn to_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+struct FenceArg<T>(u32, PhantomData<T>);
+
+impl<T> FenceArg<T> {
+    pub fn from_u32(arg: u32) -> Option<Self> {
+        if arg & !0xF == 0 {
+            Some(Self(arg, PhantomData))
+        } else {
+            None
+        }
+    }
+    pub fn to_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+struct FencePredecessor;
+
+#[derive(Debug)]
+struct FenceSuccessor;
+
+#[derive(Debug)]
+pub struct U {
+    args: (Register<Rd>, Immediate<immediate::U>),
+}
+
+#[derive(Debug)]
+pub struct J {
+    args: (Register<Rd>, Immediate<immediate::J>),
+}
+
+#[derive(Debug)]
+pub struct I {
+    args: (Register<Rd>, Register<Rs1>, Immediate<immediate::I>),
+}
+
+#[derive(Debug)]
+pub struct Fence {
+    args: (
+        Register<Rs1>,
+        Register<Rs1>,
+        FenceArg<FenceSuccessor>,
+        FenceArg<FencePredecessor>,
+        Fm,
+    ),
+}
+
+#[derive(Debug)]
+pub struct S {
+    args: (Register<Rs1>, Register<Rs2>, Immediate<immediate::S>),
+}
+
+#[derive(Debug)]
+pub struct R {
+    args: (Register<Rd>, Register<Rs1>, Register<Rs2>),
+}
+
+#[derive(Debug)]
+pub struct B {
+    args: (Register<Rs1>, Register<Rs2>, Immediate<immediate::B>),
+}
+
+#[derive(Debug)]
+pub struct Load(I);
+
+#[derive(Debug)]
+pub struct Shift {
+    args: (
+        Register<Rd>,
+        Register<Rs1>,
+        Immediate<immediate::ShiftAmount>,
+    ),
+}
+
+#[derive(Debug)]
+pub struct Csr {
+    args: (
+        Register<Rd>,
+        Register<Rs1>,
+        Immediate<immediate::CsrSpecifier>,
+    ),
+}
+
+#[derive(Debug)]
+pub struct Csri {
+    args: (
+        Register<Rd>,
+        Immediate<immediate::CsrImmediate>,
+        Immediate<immediate::CsrSpecifier>,
+    ),
+}
+
+#[derive(Debug)]
+pub enum Instruction {
+    Lui(U),
+    Auipc(U),
+    Jal(J),
+    Jalr(I),
+    Beq(B),
+    Bne(B),
+    Blt(B),
+    Bge(B),
+    Bltu(B),
+    Bgeu(B),
+    Lb(Load),
+    Lh(Load),
+    Lw(Load),
+    Lbu(Load),
+    Lhu(Load),
+    Sb(S),
+    Sh(S),
+    Sw(S),
+    Addi(I),
+    Slti(I),
+    Sltiu(I),
+    Xori(I),
+    Ori(I),
+    Andi(I),
+    Slli(Shift),
+    Srli(Shift),
+    Srai(Shift),
+    Add(R),
+    Sub(R),
+    Sll(R),
+    Slt(R),
+    Sltu(R),
+    Xor(R),
+    Srl(R),
+    Sra(R),
+    Or(R),
+    And(R),
+    Fence(Fence),
+    FenceI(I),
+    Ecall(I),
+    Ebreak(I),
+    Csrrw(Csr),
+    Csrrs(Csr),
+    Csrrc(Csr),
+    Csrrwi(Csri),
+    Csrrsi(Csri),
+    Csrrci(Csri),
+    Mul(R),
+    Mulh(R),
+    Mulhsu(R),
+    Mulhu(R),
+    Div(R),
+    Divu(R),
+    Rem(R),
+    Remu(R),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ISpecialization {
+    None,
+    Load,
+    NoArgs,
+    Csr,
+    Csri,
+    Fence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    U,
+    J,
+    I(ISpecialization),
+    B,
+    R,
+    S,
+    Shift,
+}
+
+/* The values an immediate operand can take, from its `Constraints`.
+ */
+#[derive(Debug)]
+pub struct ImmediateRange {
+    pub operand: &'static str,
+    pub min: i32,
+    pub max: i32,
+    pub even: bool,
+}
+
+impl ImmediateRange {
+    fn of<X: immediate::Constraints>(operand: &'static str) -> Self {
+        ImmediateRange {
+            operand,
+            min: X::MIN,
+            max: X::MAX,
+            even: X::EVEN,
+        }
+    }
+}
+
+impl fmt::Display for ImmediateRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}",
+            self.operand,
+            immediate::describe_range(self.min, self.max)
+        )?;
+        if self.even {
+            write!(f, ", even")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::U => write!(f, "U-type"),
+            Format::J => write!(f, "J-type"),
+            Format::I(ISpecialization::None) => write!(f, "I-type"),
+            Format::I(ISpecialization::Load) => write!(f, "I-type (load)"),
+            Format::I(ISpecialization::NoArgs) => write!(f, "I-type (no operands)"),
+            Format::I(ISpecialization::Csr) => write!(f, "I-type (CSR)"),
+            Format::I(ISpecialization::Csri) => write!(f, "I-type (CSR immediate)"),
+            Format::I(ISpecialization::Fence) => write!(f, "I-type (fence)"),
+            Format::B => write!(f, "B-type"),
+            Format::R => write!(f, "R-type"),
+            Format::S => write!(f, "S-type"),
+            Format::Shift => write!(f, "I-type (shift)"),
+        }
+    }
+}
+
+impl Format {
+    /* The ranges of the immediate operands in `operands`.
+     */
+    pub fn immediates(&self) -> Vec<ImmediateRange> {
+        match self {
+            Format::U => vec![ImmediateRange::of::<immediate::U>("imm[31:12]")],
+            Format::J => vec![ImmediateRange::of::<immediate::J>("offset[20:1]")],
+            Format::I(ISpecialization::None) => {
+                vec![ImmediateRange::of::<immediate::I>("imm[11:0]")]
+            }
+            Format::I(ISpecialization::Load) => {
+                vec![ImmediateRange::of::<immediate::I>("offset[11:0]")]
+            }
+            Format::I(ISpecialization::Csr) => {
+                vec![ImmediateRange::of::<immediate::CsrSpecifier>("csr")]
+            }
+            Format::I(ISpecialization::Csri) => vec![
+                ImmediateRange::of::<immediate::CsrSpecifier>("csr"),
+                ImmediateRange::of::<immediate::CsrImmediate>("uimm[4:0]"),
+            ],
+            Format::I(ISpecialization::NoArgs) | Format::I(ISpecialization::Fence) | Format::R => {
+                vec![]
+            }
+            Format::B => vec![ImmediateRange::of::<immediate::B>("offset[12:1]")],
+            Format::S => vec![ImmediateRange::of::<immediate::S>("offset[11:0]")],
+            Format::Shift => vec![ImmediateRange::of::<immediate::ShiftAmount>("shamt[4:0]")],
+        }
+    }
+
+    /* The operands an instruction of this format is written with.
+     */
+    pub fn operands(&self) -> &'static str {
+        match self {
+            Format::U => "rd, imm[31:12]",
+            Format::J => "rd, offset[20:1]",
+            Format::I(ISpecialization::None) => "rd, rs1, imm[11:0]",
+            Format::I(ISpecialization::Load) => "rd, offset[11:0](rs1)",
+            Format::I(ISpecialization::NoArgs) => "",
+            Format::I(ISpecialization::Csr) => "rd, csr, rs1",
+            Format::I(ISpecialization::Csri) => "rd, csr, uimm[4:0]",
+            Format::I(ISpecialization::Fence) => "pred, succ",
+            Format::B => "rs1, rs2, offset[12:1]",
+            Format::R => "rd, rs1, rs2",
+            Format::S => "rs2, offset[11:0](rs1)",
+            Format::Shift => "rd, rs1, shamt[4:0]",
+        }
+    }
+}
+
+trait Placeable {
+    const MASK: u32;
+    fn place_unchecked(&self) -> u32;
+    fn place(&self) -> u32 {
+        let bits = self.place_unchecked();
+        if bits & !Self::MASK == 0 {
+            bits
+        } else {
+            panic!(
+                "Placed bits (0x{:08X}) do not fit within the mask (0x{:08X}) .",
+                bits,
+                Self::MASK
+            );
+        }
+    }
+}
+
+pub trait RegisterPlacement {
+    const OFFSET: u32;
+}
+
+impl<R: RegisterPlacement> Placeable for Register<R> {
+    const MASK: u32 = 0b11111 << R::OFFSET;
+
+    fn place_unchecked(&self) -> u32 {
+        self.to_u32() << R::OFFSET
+    }
+}
+
+impl RegisterPlacement for Rd {
+    const OFFSET: u32 = 7;
+}
+
+impl RegisterPlacement for Rs1 {
+    const OFFSET: u32 = 15;
+}
+
+impl RegisterPlacement for Rs2 {
+    const OFFSET: u32 = 20;
+}
+
+impl Placeable for Immediate<immediate::U> {
+    const MASK: u32 = 0xFFFFF << 12;
+
+    fn place_unchecked(&self) -> u32 {
+        (self.to_i32() as u32) << 12
+    }
+}
+
+impl Placeable for Immediate<immediate::I> {
+    const MASK: u32 = 0xFFF << 20;
+
+    fn place_unchecked(&self) -> u32 {
+        (self.to_i32() as u32 & 0xFFF) << 20
+    }
+}
+
+impl Placeable for Immediate<immediate::S> {
+    const MASK: u32 = 0xFE000F80;
+
+    fn place_unchecked(&self) -> u32 {
+        let imm_ = self.to_i32() as u32;
+
+        // imm[11:5] -> inst[31:25]
+        // imm[4:0]  -> inst[11:7]
+        ((imm_ & 0b111111100000) << 20) | ((imm_ & 0b000000011111) << 07)
+    }
+}
+
+impl Placeable for Immediate<immediate::J> {
+    const MASK: u32 = 0xFFFFF000;
+
+    fn place_unchecked(&self) -> u32 {
+        let imm_ = self.to_i32() as u32;
+        // imm[20]    -> inst[31]
+        // imm[10:1]  -> inst[30:21]
+        // imm[11]    -> inst[20]
+        // imm[19:12] ->, inst[19:12]
+        000 | ((imm_ & 0x100000) << 11)
+            | ((imm_ & 0x0007FE) << 20)
+            | ((imm_ & 0x000800) << 09)
+            | ((imm_ & 0x0FF000) << 00)
+    }
+}
+
+impl Placeable for Immediate<immediate::B> {
+    const MASK: u32 = 0xFE000F80;
+
+    fn place_unchecked(&self) -> u32 {
+        let imm_ = self.to_i32() as u32;
+        // imm[12]   -> inst[31]
+        // imm[10:5] -> inst[30:25]
+        // imm[4:1]  -> inst[11:8]
+        // imm[11]   -> inst[7]
+        000 | ((imm_ & 0b1000000000000) << 19)
+            | ((imm_ & 0b0011111100000) << 20)
+            | ((imm_ & 0b0000000011110) << 07)
+            | ((imm_ & 0b0100000000000) >> 04)
+    }
+}
+
+impl Placeable for Immediate<immediate::ShiftAmount> {
+    const MASK: u32 = 0x01F00000;
+
+    fn place_unchecked(&self) -> u32 {
+        (self.to_i32() as u32) << 20
+    }
+}
+
+impl Placeable for Opcode {
+    const MASK: u32 = 0x7F;
+
+    fn place_unchecked(&self) -> u32 {
+        self.to_u32()
+    }
+}
+
+impl Placeable for Funct3 {
+    const MASK: u32 = 0x7000;
+
+    fn place_unchecked(&self) -> u32 {
+        self.to_u32() << 12
+    }
+}
+
+impl Placeable for Funct7 {
+    const MASK: u32 = 0xFE000000;
+
+    fn place_unchecked(&self) -> u32 {
+        self.to_u32() << 25
+    }
+}
+
+impl Placeable for Fm {
+    const MASK: u32 = 0xF0000000;
+
+    fn place_unchecked(&self) -> u32 {
+        self.to_u32() << 28
+    }
+}
+
+impl Placeable for FenceArg<FenceSuccessor> {
+    const MASK: u32 = 0x00F00000;
+
+    fn place_unchecked(&self) -> u32 {
+        self.to_u32() << 20
+    }
+}
+
+impl Placeable for FenceArg<FencePredecessor> {
+    const MASK: u32 = 0x0F000000;
+
+    fn place_unchecked(&self) -> u32 {
+        self.to_u32() << 24
+    }
+}
+
+impl<FT> fmt::Display for FenceArg<FT> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fence_arg = [
+            if (self.0 & 0b1000) == 0 { "" } else { "i" },
+            if (self.0 & 0b0100) == 0 { "" } else { "o" },
+            if (self.0 & 0b0010) == 0 { "" } else { "r" },
+            if (self.0 & 0b0001) == 0 { "" } else { "w" },
+        ]
+        .concat();
+        write!(f, "{}", fence_arg)
+    }
+}
+
+impl Placeable for Immediate<immediate::CsrSpecifier> {
+    const MASK: u32 = Immediate::<immediate::I>::MASK;
+
+    fn place_unchecked(&self) -> u32 {
+        (self.to_i32() as u32) << 20
+    }
+}
+
+impl Placeable for Immediate<immediate::CsrImmediate> {
+    const MASK: u32 = Register::<Rs1>::MASK;
+
+    fn place_unchecked(&self) -> u32 {
+        (self.to_i32() as u32) << 15
+    }
+}
+
+/* The inverse of `Placeable`: pulls a field back out of an encoded
+ * instruction. Immediates are sign extended where the format requires it.
+ */
+trait Extractable {
+    fn extract(inst: u32) -> Self;
+}
+
+impl<R: RegisterPlacement> Extractable for Register<R> {
+    fn extract(inst: u32) -> Self {
+        Register::from_u32((inst >> R::OFFSET) & 0b11111).unwrap()
+    }
+}
+
+impl Extractable for Immediate<immediate::U> {
+    fn extract(inst: u32) -> Self {
+        Immediate::from_i32((inst >> 12) as i32).unwrap()
+    }
+}
+
+impl Extractable for Immediate<immediate::I> {
+    fn extract(inst: u32) -> Self {
+        Immediate::from_i32((inst as i32) >> 20).unwrap()
+    }
+}
+
+impl Extractable for Immediate<immediate::S> {
+    fn extract(inst: u32) -> Self {
+        // inst[31:25] -> imm[11:5]
+        // inst[11:7]  -> imm[4:0]
+        Immediate::from_i32(((inst as i32) >> 20) & !0x1F | ((inst >> 7) & 0x1F) as i32).unwrap()
+    }
+}
+
+impl Extractable for Immediate<immediate::J> {
+    fn extract(inst: u32) -> Self {
+        // inst[31]    -> imm[20]
+        // inst[30:21] -> imm[10:1]
+        // inst[20]    -> imm[11]
+        // inst[19:12] -> imm[19:12]
+        Immediate::from_i32(
+            ((inst as i32) >> 11) & !0xFFFFF
+                | ((inst >> 20) & 0x0007FE) as i32
+                | ((inst >> 9) & 0x000800) as i32
+                | (inst & 0x0FF000) as i32,
+        )
+        .unwrap()
+    }
+}
+
+impl Extractable for Immediate<immediate::B> {
+    fn extract(inst: u32) -> Self {
+        // inst[31]    -> imm[12]
+        // inst[30:25] -> imm[10:5]
+        // inst[11:8]  -> imm[4:1]
+        // inst[7]     -> imm[11]
+        Immediate::from_i32(
+            ((inst as i32) >> 19) & !0xFFF
+                | ((inst >> 20) & 0b0011111100000) as i32
+                | ((inst >> 7) & 0b0000000011110) as i32
+                | ((inst << 4) & 0b0100000000000) as i32,
+        )
+        .unwrap()
+    }
+}
+
+impl Extractable for Immediate<immediate::ShiftAmount> {
+    fn extract(inst: u32) -> Self {
+        Immediate::from_i32(((inst >> 20) & 0x1F) as i32).unwrap()
+    }
+}
+
+impl Extractable for Immediate<immediate::CsrSpecifier> {
+    fn extract(inst: u32) -> Self {
+        Immediate::from_i32((inst >> 20) as i32).unwrap()
+    }
+}
+
+impl Extractable for Immediate<immediate::CsrImmediate> {
+    fn extract(inst: u32) -> Self {
+        Immediate::from_i32(((inst >> 15) & 0x1F) as i32).unwrap()
+    }
+}
+
+impl Extractable for Opcode {
+    fn extract(inst: u32) -> Self {
+        Opcode::from_u32(inst & 0x7F).unwrap()
+    }
+}
+
+impl Extractable for Funct3 {
+    fn extract(inst: u32) -> Self {
+        Funct3::from_u32((inst >> 12) & 0x7).unwrap()
+    }
+}
+
+impl Extractable for Funct7 {
+    fn extract(inst: u32) -> Self {
+        Funct7::from_u32(inst >> 25).unwrap()
+    }
+}
+
+impl Extractable for Fm {
+    fn extract(inst: u32) -> Self {
+        Fm::from_u32(inst >> 28).unwrap()
+    }
+}
+
+impl Extractable for FenceArg<FenceSuccessor> {
+    fn extract(inst: u32) -> Self {
+        FenceArg::from_u32((inst >> 20) & 0xF).unwrap()
+    }
+}
+
+impl Extractable for FenceArg<FencePredecessor> {
+    fn extract(inst: u32) -> Self {
+        FenceArg::from_u32((inst >> 24) & 0xF).unwrap()
+    }
+}
+
+impl U {
+    pub fn new(rd: Register<Rd>, imm: Immediate<immediate::U>) -> Self {
+        Self { args: (rd, imm) }
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                Some(|rd, imm| {
+                    let rd = Register::from_str(rd)
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let imm = Immediate::from_str(imm)
+                        .map_err(|e| Error::InvalidArgument(1, InvalidArgument::Immediate(e)))?;
+
+                    Ok(U { args: (rd, imm) })
+                }),
+                None,
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode) -> u32 {
+        let (rd, imm) = &self.args;
+        opcode.place() | rd.place() | imm.place()
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self {
+            args: (Register::extract(inst), Immediate::extract(inst)),
+        }
+    }
+}
+
+impl fmt::Display for U {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rd, imm) = &self.args;
+
+        write!(
+            f,
+            "{rd},{imm}", // TODO: limited to 12 bits?
+            rd = rd,
+            imm = imm,
+        )
+    }
+}
+
+impl J {
+    pub fn new(rd: Register<Rd>, imm: Immediate<immediate::J>) -> Self {
+        Self { args: (rd, imm) }
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                Some(|rd, imm| {
+                    let rd = Register::from_str(rd)
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let imm = Immediate::from_str(imm)
+                        .map_err(|e| Error::InvalidArgument(1, InvalidArgument::Immediate(e)))?;
+
+                    Ok(J { args: (rd, imm) })
+                }),
+                None,
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode) -> u32 {
+        let (rd, imm) = &self.args;
+        opcode.place() | rd.place() | imm.place()
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self {
+            args: (Register::extract(inst), Immediate::extract(inst)),
+        }
+    }
+}
+
+impl fmt::Display for J {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rd, imm) = &self.args;
+
+        write!(
+            f,
+            "{rd},{imm}", // TODO: limited to 12 bits?
+            rd = rd,
+            imm = imm
+        )
+    }
+}
+
+pub(crate) fn get_memory_argument<Im>(
+    memory_location: &str,
+) -> Result<(Register<Rs1>, Immediate<Im>), InvalidArgument>
+where
+    Im: immediate::Constraints,
+{
+    if let Some(open_bracket_index) = memory_location.find('(') {
+        let close_bracket_index =
+            memory_location
+                .find(')')
+                .ok_or(InvalidArgument::MemoryLocation(
+                    GetMemoryLocationError::MissingCloseParentheses,
+                ))?;
+
+        if close_bracket_index != memory_location.len() - 1 {
+            return Err(InvalidArgument::MemoryLocation(
+                GetMemoryLocationError::TextAfterCloseParenthesis,
+            ));
+        }
+
+        let rs1 = Register::from_str(&memory_location[open_bracket_index + 1..close_bracket_index])
+            .map_err(InvalidArgument::Register)?;
+
+        let offset = if open_bracket_index == 0 {
+            Immediate::from_i32(0).unwrap()
+        } else {
+            Immediate::from_str(&memory_location[0..open_bracket_index])
+                .map_err(InvalidArgument::Immediate)?
+        };
+        Ok((rs1, offset))
+    } else {
+        let rs1 = Register::from_str(&memory_location).map_err(InvalidArgument::Register)?;
+
+        let offset = Immediate::from_i32(0).unwrap();
+
+        Ok((rs1, offset))
+    }
+}
+
+impl I {
+    pub fn new(rd: Register<Rd>, rs1: Register<Rs1>, imm: Immediate<immediate::I>) -> Self {
+        Self {
+            args: (rd, rs1, imm),
+        }
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                None,
+                Some(|rd, rs1, imm| {
+                    let rd = Register::from_str(rd)
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let rs1 = Register::from_str(rs1)
+                        .map_err(|e| Error::InvalidArgument(1, InvalidArgument::Register(e)))?;
+                    let imm = Immediate::from_str(imm)
+                        .map_err(|e| Error::InvalidArgument(2, InvalidArgument::Immediate(e)))?;
+
+                    Ok(I {
+                        args: (rd, rs1, imm),
+                    })
+                }),
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode, funct3: &Funct3) -> u32 {
+        let (rd, rs1, imm) = &self.args;
+        opcode.place() | rd.place() | funct3.place() | rs1.place() | imm.place()
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self {
+            args: (
+                Register::extract(inst),
+                Register::extract(inst),
+                Immediate::extract(inst),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for I {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rd, rs1, imm) = &self.args;
+
+        write!(
+            f,
+            "{rd},{rs1},{imm}", // TODO: limited to 12 bits?
+            rd = rd,
+            rs1 = rs1,
+            imm = imm
+        )
+    }
+}
+
+impl S {
+    pub fn new(rs1: Register<Rs1>, rs2: Register<Rs2>, imm: Immediate<immediate::S>) -> Self {
+        Self {
+            args: (rs1, rs2, imm),
+        }
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                Some(|rs2, mem_arg| {
+                    let rs2 = Register::from_str(rs2)
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let (rs1, offset) =
+                        get_memory_argument(mem_arg).map_err(|e| Error::InvalidArgument(1, e))?;
+
+                    Ok(S {
+                        args: (rs1, rs2, offset),
+                    })
+                }),
+                None,
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode, funct3: &Funct3) -> u32 {
+        let (rs1, rs2, imm) = &self.args;
+        opcode.place() | funct3.place() | rs1.place() | rs2.place() | imm.place()
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self {
+            args: (
+                Register::extract(inst),
+                Register::extract(inst),
+                Immediate::extract(inst),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for S {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rs1, rs2, imm) = &self.args;
+
+        write!(
+            f,
+            "{rs2},{imm}({rs1})", // TODO: limited to 12 bits?
+            rs1 = rs1,
+            rs2 = rs2,
+            imm = imm,
+        )
+    }
+}
+
+impl Load {
+    pub fn new(rd: Register<Rd>, rs1: Register<Rs1>, imm: Immediate<immediate::I>) -> Self {
+        Self(I::new(rd, rs1, imm))
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                Some(|rd, mem_arg| {
+                    let rd = Register::from_str(rd)
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let (rs1, offset) =
+                        get_memory_argument(mem_arg).map_err(|e| Error::InvalidArgument(1, e))?;
+
+                    Ok(Self(I {
+                        args: (rd, rs1, offset),
+                    }))
+                }),
+                None,
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode, funct3: &Funct3) -> u32 {
+        self.0.to_u32(opcode, funct3)
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self(I::from_u32(inst))
+    }
+}
+
+impl fmt::Display for Load {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rd, rs1, imm) = &self.0.args;
+
+        write!(f, "{rd},{imm}({rs1})", rs1 = rs1, rd = rd, imm = imm,)
+    }
+}
+
+impl R {
+    pub fn new(rd: Register<Rd>, rs1: Register<Rs1>, rs2: Register<Rs2>) -> Self {
+        Self {
+            args: (rd, rs1, rs2),
+        }
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                None,
+                Some(|rd, rs1, rs2| {
+                    let rd = Register::from_str(rd)
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let rs1 = Register::from_str(rs1)
+                        .map_err(|e| Error::InvalidArgument(1, InvalidArgument::Register(e)))?;
+                    let rs2 = Register::from_str(rs2)
+                        .map_err(|e| Error::InvalidArgument(2, InvalidArgument::Register(e)))?;
+
+                    Ok(R {
+                        args: (rd, rs1, rs2),
+                    })
+                }),
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode, funct3: &Funct3, funct7: &Funct7) -> u32 {
+        let (rd, rs1, rs2) = &self.args;
+        opcode.place() | rd.place() | funct3.place() | rs1.place() | rs2.place() | funct7.place()
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self {
+            args: (
+                Register::extract(inst),
+                Register::extract(inst),
+                Register::extract(inst),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for R {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rd, rs1, rs2) = &self.args;
+
+        write!(f, "{rd},{rs1},{rs2}", rd = rd, rs1 = rs1, rs2 = rs2,)
+    }
+}
+
+impl B {
+    pub fn new(rs1: Register<Rs1>, rs2: Register<Rs2>, imm: Immediate<immediate::B>) -> Self {
+        Self {
+            args: (rs1, rs2, imm),
+        }
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                None,
+                Some(|rs1, rs2, imm| {
+                    let rs1 = Register::from_str(rs1)
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let rs2 = Register::from_str(rs2)
+                        .map_err(|e| Error::InvalidArgument(1, InvalidArgument::Register(e)))?;
+                    let imm = Immediate::from_str(imm)
+                        .map_err(|e| Error::InvalidArgument(2, InvalidArgument::Immediate(e)))?;
+
+                    Ok(B {
+                        args: (rs1, rs2, imm),
+                    })
+                }),
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode, funct3: &Funct3) -> u32 {
+        let (rs1, rs2, imm) = &self.args;
+        opcode.place() | imm.place() | funct3.place() | rs1.place() | rs2.place()
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self {
+            args: (
+                Register::extract(inst),
+                Register::extract(inst),
+                Immediate::extract(inst),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for B {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rs1, rs2, imm) = &self.args;
+
+        write!(f, "{rs1},{rs2},{imm}", rs1 = rs1, rs2 = rs2, imm = imm)
+    }
+}
+
+impl<T> FromStr for FenceArg<T> {
+    type Err = ();
+
+    fn from_str<'a>(mut arg: &str) -> Result<Self, ()> {
+        // TODO: surely we can make this nicer?
+        let mut fence_arg = 0;
+        let mut possible_characters: &[char] = &['i', 'o', 'r', 'w'];
+
+        while let Some((c, rest)) = possible_characters.split_first() {
+            possible_characters = rest;
+            fence_arg <<= 1;
+            if arg.contains(*c) {
+                if arg.starts_with(*c) {
+                    fence_arg |= 1;
+                    let tuple = arg.split_at(1);
+                    arg = tuple.1;
+                } else {
+                    return Err(());
+                }
+            }
+        }
+        if fence_arg == 0 {
+            return Err(());
+        }
+        if arg != "" {
+            return Err(());
+        }
+        Ok(FenceArg::from_u32(fence_arg).unwrap())
+    }
+}
+
+impl Fence {
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                Some(|| {
+                    Ok(Fence {
+                        args: (
+                            Register::ZERO,
+                            Register::ZERO,
+                            FenceArg::from_u32(0b1111).unwrap(),
+                            FenceArg::from_u32(0b1111).unwrap(),
+                            Fm::from_u32(0).unwrap(),
+                        ),
+                    })
+                }),
+                None,
+                Some(|arg0: &str, arg1: &str| {
+                    let pred: FenceArg<FencePredecessor> = arg0.parse().or(Err(
+                        Error::InvalidArgument(0, InvalidArgument::Fence(arg0.to_string())),
+                    ))?;
+                    let succ: FenceArg<FenceSuccessor> = arg1.parse().or(Err(
+                        Error::InvalidArgument(1, InvalidArgument::Fence(arg1.to_string())),
+                    ))?;
+
+                    Ok(Fence {
+                        args: (
+                            Register::ZERO,
+                            Register::ZERO,
+                            succ,
+                            pred,
+                            Fm::from_u32(0).unwrap(),
+                        ),
+                    })
+                }),
+                None,
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode, funct3: &Funct3) -> u32 {
+        let (rd, rs1, succ, pred, rm) = &self.args;
+        opcode.place()
+            | rd.place()
+            | funct3.place()
+            | rs1.place()
+            | succ.place()
+            | pred.place()
+            | rm.place()
+    }
+
+    fn from_u32(inst: u32) -> Result<Self, DecodeError> {
+        // Only rs1 is stored, the rd field of a fence must be left as zero.
+        let rd_bits = Register::<Rd>::extract(inst).place();
+        if rd_bits != 0 {
+            return Err(DecodeError::ReservedBitsSet { mask: rd_bits });
+        }
+        Ok(Self {
+            args: (
+                Register::ZERO,
+                Register::extract(inst),
+                FenceArg::extract(inst),
+                FenceArg::extract(inst),
+                Fm::extract(inst),
+            ),
+        })
+    }
+}
+
+impl fmt::Display for Fence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (_, _, succ, pred, _) = &self.args;
+
+        write!(f, "{pred}, {succ}", pred = pred, succ = succ)
+    }
+}
+
+impl Shift {
+    pub fn new(
+        rd: Register<Rd>,
+        rs1: Register<Rs1>,
+        shamt: Immediate<immediate::ShiftAmount>,
+    ) -> Self {
+        Self {
+            args: (rd, rs1, shamt),
+        }
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                None,
+                Some(|rd: &str, rs1: &str, shamt: &str| {
+                    let rd: Register<Rd> = rd
+                        .parse()
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let rs1: Register<Rs1> = rs1
+                        .parse()
+                        .map_err(|e| Error::InvalidArgument(1, InvalidArgument::Register(e)))?;
+                    let shamt: Immediate<immediate::ShiftAmount> = shamt
+                        .parse()
+                        .map_err(|e| Error::InvalidArgument(2, InvalidArgument::Immediate(e)))?;
+
+                    Ok(Self {
+                        args: (rd, rs1, shamt),
+                    })
+                }),
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode, funct3: &Funct3, funct7: &Funct7) -> u32 {
+        let (shamt, rs1, rd) = &self.args;
+        opcode.place() | rd.place() | funct3.place() | rs1.place() | shamt.place() | funct7.place()
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self {
+            args: (
+                Register::extract(inst),
+                Register::extract(inst),
+                Immediate::extract(inst),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Shift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rd, rs1, shamt) = &self.args;
+
+        write!(f, "{rd},{rs1}, {shamt}", rd = rd, rs1 = rs1, shamt = shamt,)
+    }
+}
+
+impl Csr {
+    pub fn new(
+        rd: Register<Rd>,
+        rs1: Register<Rs1>,
+        csr: Immediate<immediate::CsrSpecifier>,
+    ) -> Self {
+        Self {
+            args: (rd, rs1, csr),
+        }
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                None,
+                Some(|rd: &str, csr_specifier: &str, rs1: &str| {
+                    let rd: Register<Rd> = rd
+                        .parse()
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let csr_specifier: Immediate<immediate::CsrSpecifier> =
+                        csr_specifier.parse().map_err(|e| {
+                            Error::InvalidArgument(1, InvalidArgument::Immediate(e))
+                        })?;
+                    let rs1: Register<Rs1> = rs1
+                        .parse()
+                        .map_err(|e| Error::InvalidArgument(2, InvalidArgument::Register(e)))?;
+
+                    Ok(Self {
+                        args: (rd, rs1, csr_specifier),
+                    })
+                }),
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode, funct3: &Funct3) -> u32 {
+        let (rd, rs1, csr_specifier) = &self.args;
+        opcode.place() | rd.place() | funct3.place() | rs1.place() | csr_specifier.place()
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self {
+            args: (
+                Register::extract(inst),
+                Register::extract(inst),
+                Immediate::extract(inst),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Csr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rd, rs1, csr) = &self.args;
+
+        write!(f, "{rd}, {csr}, {rs1}", rd = rd, rs1 = rs1, csr = csr,)
+    }
+}
+
+impl Csri {
+    pub fn new(
+        rd: Register<Rd>,
+        imm: Immediate<immediate::CsrImmediate>,
+        csr: Immediate<immediate::CsrSpecifier>,
+    ) -> Self {
+        Self {
+            args: (rd, imm, csr),
+        }
+    }
+
+    fn from_args(args: &[&str]) -> Result<Self, Error> {
+        parse_help(
+            args,
+            (
+                None,
+                None,
+                None,
+                Some(|rd: &str, csr_specifier: &str, imm: &str| {
+                    let rd: Register<Rd> = rd
+                        .parse()
+                        .map_err(|e| Error::InvalidArgument(0, InvalidArgument::Register(e)))?;
+                    let csr_specifier: Immediate<immediate::CsrSpecifier> =
+                        csr_specifier.parse().map_err(|e| {
+                            Error::InvalidArgument(1, InvalidArgument::Immediate(e))
+                        })?;
+                    let imm: Immediate<immediate::CsrImmediate> = imm
+                        .parse()
+                        .map_err(|e| Error::InvalidArgument(2, InvalidArgument::Immediate(e)))?;
+
+                    Ok(Self {
+                        args: (rd, imm, csr_specifier),
+                    })
+                }),
+            ),
+        )
+    }
+
+    fn to_u32(&self, opcode: &Opcode, funct3: &Funct3) -> u32 {
+        let (rd, imm, csr_specifier) = &self.args;
+        opcode.place() | rd.place() | funct3.place() | imm.place() | csr_specifier.place()
+    }
+
+    fn from_u32(inst: u32) -> Self {
+        Self {
+            args: (
+                Register::extract(inst),
+                Immediate::extract(inst),
+                Immediate::extract(inst),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Csri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (rd, imm, csr) = &self.args;
+
+        write!(f, "{rd}, {csr}, {imm}", rd = rd, csr = csr, imm = imm,)
+    }
+}
+
+/* Splits `name arg0, arg1, ...` into the name and a list of arguments.
+ */
+pub(crate) fn split_mnemonic(mnemonic: &str) -> (&str, Vec<&str>) {
+    let mnemonic = mnemonic.trim();
+
+    let first_space_index = mnemonic.find(' ').unwrap_or(mnemonic.len());
+
+    let (name, args) = mnemonic.split_at(first_space_index);
+
+    let args: Vec<&str> = if args.is_empty() {
+        vec![]
+    } else {
+        args.split(',').map(str::trim).collect()
+    };
+
+    (name, args)
+}
+
+/* The spans of the name and arguments that `split_mnemonic` returns.
+ */
+pub fn split_mnemonic_spans(mnemonic: &str) -> (Span, Vec<Span>) {
+    let leading = mnemonic.len() - mnemonic.trim_start().len();
+    let trimmed = mnemonic.trim();
+
+    let first_space_index = trimmed.find(' ').unwrap_or(trimmed.len());
+    let name = Span::new(leading, leading + first_space_index);
+
+    let mut args = vec![];
+    if first_space_index != trimmed.len() {
+        let mut offset = leading + first_space_index;
+        for arg in trimmed[first_space_index..].split(',') {
+            let start = offset + arg.len() - arg.trim_start().len();
+            args.push(Span::new(start, start + arg.trim().len()));
+            offset += arg.len() + 1;
+        }
+    }
+
+    (name, args)
+}
+
+/* An `InvalidInstructionName` error suggesting the closest of `mnemonics`.
+ */
+pub(crate) fn invalid_instruction_name<M>(name: &str, mnemonics: M) -> Error
+where
+    M: IntoIterator<Item = &'static str>,
+{
+    Error::InvalidInstructionName {
+        name: name.to_string(),
+        suggestion: diagnostic::closest(name, mnemonics),
+    }
+}
+
+pub(crate) fn parse_register<R>(index: u32, string: &str) -> Result<Register<R>, Error> {
+    Register::from_str(string)
+        .map_err(|e| Error::InvalidArgument(index, InvalidArgument::Register(e)))
+}
+
+pub(crate) fn parse_immediate<X: immediate::Constraints>(
+    index: u32,
+    string: &str,
+) -> Result<Immediate<X>, Error> {
+    Immediate::from_str(string)
+        .map_err(|e| Error::InvalidArgument(index, InvalidArgument::Immediate(e)))
+}
+
+pub(crate) fn parse_help<In>(
+    args: &[&str],
+    (f0, f1, f2, f3): (
+        Option<fn() -> Result<In, Error>>,
+        Option<fn(&str) -> Result<In, Error>>,
+        Option<fn(&str, &str) -> Result<In, Error>>,
+        Option<fn(&str, &str, &str) -> Result<In, Error>>,
+    ),
+) -> Result<In, Error>
+where
+    // F1: fn(&str) -> Result<In, Error>,
+    // F2: fn(&str, &str) -> Result<In, Error>,
+    // F3: fn(&str, &str, &str) -> Result<In, Error>,
+{
+    let valid_lengths = {
+        let mut tmp = vec![];
+        if f0.is_some() {
+            tmp.push(0);
+        }
+        if f1.is_some() {
+            tmp.push(1);
+        }
+        if f2.is_some() {
+            tmp.push(2);
+        }
+        if f3.is_some() {
+            tmp.push(3);
+        }
+        tmp
+    };
+    match args.len() {
+        0 => {
+            if let Some(sf0) = f0 {
+                return sf0();
+            }
+        }
+        1 => {
+            if let Some(sf1) = f1 {
+                return sf1(args[0]);
+            }
+        }
+        2 => {
+            if let Some(sf2) = f2 {
+                return sf2(args[0], args[1]);
+            }
+        }
+        3 => {
+            if let Some(sf3) = f3 {
+                return sf3(args[0], args[1], args[2]);
+            }
+        }
+        _ => {}
+    };
+    Result::Err(Error::WrongNumberOfArgs {
+        actual: args.len(),
+        expected: valid_lengths,
+    })
+}
+
+fn parse_no_args(args: &[&str]) -> Result<I, Error> {
+    parse_help(
+        args,
+        (
+            Some(|| {
+                Ok(I {
+                    args: (
+                        Register::ZERO,
+                        Register::ZERO,
+                        Immediate::from_i32(0).unwrap(),
+                    ),
+                })
+            }),
+            None,
+            None,
+            None,
+        ),
+    )
+}
+
+/* `li rd, imm` for an immediate that fits in a single `addi`. Larger
+ * immediates are expanded by `pseudo::parse`.
+ */
+fn parse_li(args: &[&str]) -> Result<I, Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            Some(|rd, imm| {
+                Ok(I {
+                    args: (
+                        parse_register(0, rd)?,
+                        Register::ZERO,
+                        parse_immediate(1, imm)?,
+                    ),
+                })
+            }),
+            None,
+        ),
+    )
+}
+
+/* Information about each instruction that `Instruction::from_str` accepts,
+ * apart from `nop` and `li`, which it reads as `addi`. The description is the instruction's effect, written in terms of the
+ * operand names from `Format::operands`.
+ */
+#[derive(Debug)]
+pub struct Metadata {
+    pub mnemonic: &'static str,
+    pub format: Format,
+    pub description: &'static str,
+}
+
+pub const INSTRUCTIONS: &[Metadata] = &[
+    Metadata {
+        mnemonic: "lui",
+        format: Format::U,
+        description: "rd = imm << 12",
+    },
+    Metadata {
+        mnemonic: "auipc",
+        format: Format::U,
+        description: "rd = pc + (imm << 12)",
+    },
+    Metadata {
+        mnemonic: "jal",
+        format: Format::J,
+        description: "rd = pc + 4; pc += sext(offset)",
+    },
+    Metadata {
+        mnemonic: "jalr",
+        format: Format::I(ISpecialization::None),
+        description: "rd = pc + 4; pc = (rs1 + sext(imm)) & ~1",
+    },
+    Metadata {
+        mnemonic: "beq",
+        format: Format::B,
+        description: "if (rs1 == rs2) pc += sext(offset)",
+    },
+    Metadata {
+        mnemonic: "bne",
+        format: Format::B,
+        description: "if (rs1 != rs2) pc += sext(offset)",
+    },
+    Metadata {
+        mnemonic: "blt",
+        format: Format::B,
+        description: "if (rs1 <s rs2) pc += sext(offset)",
+    },
+    Metadata {
+        mnemonic: "bge",
+        format: Format::B,
+        description: "if (rs1 >=s rs2) pc += sext(offset)",
+    },
+    Metadata {
+        mnemonic: "bltu",
+        format: Format::B,
+        description: "if (rs1 <u rs2) pc += sext(offset)",
+    },
+    Metadata {
+        mnemonic: "bgeu",
+        format: Format::B,
+        description: "if (rs1 >=u rs2) pc += sext(offset)",
+    },
+    Metadata {
+        mnemonic: "lb",
+        format: Format::I(ISpecialization::Load),
+        description: "rd = sext(M[rs1 + sext(offset)][7:0])",
+    },
+    Metadata {
+        mnemonic: "lh",
+        format: Format::I(ISpecialization::Load),
+        description: "rd = sext(M[rs1 + sext(offset)][15:0])",
+    },
+    Metadata {
+        mnemonic: "lw",
+        format: Format::I(ISpecialization::Load),
+        description: "rd = M[rs1 + sext(offset)][31:0]",
+    },
+    Metadata {
+        mnemonic: "lbu",
+        format: Format::I(ISpecialization::Load),
+        description: "rd = zext(M[rs1 + sext(offset)][7:0])",
+    },
+    Metadata {
+        mnemonic: "lhu",
+        format: Format::I(ISpecialization::Load),
+        description: "rd = zext(M[rs1 + sext(offset)][15:0])",
+    },
+    Metadata {
+        mnemonic: "sb",
+        format: Format::S,
+        description: "M[rs1 + sext(offset)][7:0] = rs2[7:0]",
+    },
+    Metadata {
+        mnemonic: "sh",
+        format: Format::S,
+        description: "M[rs1 + sext(offset)][15:0] = rs2[15:0]",
+    },
+    Metadata {
+        mnemonic: "sw",
+        format: Format::S,
+        description: "M[rs1 + sext(offset)][31:0] = rs2",
+    },
+    Metadata {
+        mnemonic: "addi",
+        format: Format::I(ISpecialization::None),
+        description: "rd = rs1 + sext(imm)",
+    },
+    Metadata {
+        mnemonic: "slti",
+        format: Format::I(ISpecialization::None),
+        description: "rd = (rs1 <s sext(imm)) ? 1 : 0",
+    },
+    Metadata {
+        mnemonic: "sltiu",
+        format: Format::I(ISpecialization::None),
+        description: "rd = (rs1 <u sext(imm)) ? 1 : 0",
+    },
+    Metadata {
+        mnemonic: "xori",
+        format: Format::I(ISpecialization::None),
+        description: "rd = rs1 ^ sext(imm)",
+    },
+    Metadata {
+        mnemonic: "ori",
+        format: Format::I(ISpecialization::None),
+        description: "rd = rs1 | sext(imm)",
+    },
+    Metadata {
+        mnemonic: "andi",
+        format: Format::I(ISpecialization::None),
+        description: "rd = rs1 & sext(imm)",
+    },
+    Metadata {
+        mnemonic: "slli",
+        format: Format::Shift,
+        description: "rd = rs1 << shamt",
+    },
+    Metadata {
+        mnemonic: "srli",
+        format: Format::Shift,
+        description: "rd = rs1 >>u shamt",
+    },
+    Metadata {
+        mnemonic: "srai",
+        format: Format::Shift,
+        description: "rd = rs1 >>s shamt",
+    },
+    Metadata {
+        mnemonic: "add",
+        format: Format::R,
+        description: "rd = rs1 + rs2",
+    },
+    Metadata {
+        mnemonic: "sub",
+        format: Format::R,
+        description: "rd = rs1 - rs2",
+    },
+    Metadata {
+        mnemonic: "sll",
+        format: Format::R,
+        description: "rd = rs1 << rs2[4:0]",
+    },
+    Metadata {
+        mnemonic: "slt",
+        format: Format::R,
+        description: "rd = (rs1 <s rs2) ? 1 : 0",
+    },
+    Metadata {
+        mnemonic: "sltu",
+        format: Format::R,
+        description: "rd = (rs1 <u rs2) ? 1 : 0",
+    },
+    Metadata {
+        mnemonic: "xor",
+        format: Format::R,
+        description: "rd = rs1 ^ rs2",
+    },
+    Metadata {
+        mnemonic: "srl",
+        format: Format::R,
+        description: "rd = rs1 >>u rs2[4:0]",
+    },
+    Metadata {
+        mnemonic: "sra",
+        format: Format::R,
+        description: "rd = rs1 >>s rs2[4:0]",
+    },
+    Metadata {
+        mnemonic: "or",
+        format: Format::R,
+        description: "rd = rs1 | rs2",
+    },
+    Metadata {
+        mnemonic: "and",
+        format: Format::R,
+        description: "rd = rs1 & rs2",
+    },
+    Metadata {
+        mnemonic: "fence",
+        format: Format::I(ISpecialization::Fence),
+        description: "Order the pred memory and I/O accesses before the succ accesses",
+    },
+    Metadata {
+        mnemonic: "fence.i",
+        format: Format::I(ISpecialization::NoArgs),
+        description: "Synchronise the instruction and data streams",
+    },
+    Metadata {
+        mnemonic: "ecall",
+        format: Format::I(ISpecialization::NoArgs),
+        description: "Request a service from the execution environment",
+    },
+    Metadata {
+        mnemonic: "ebreak",
+        format: Format::I(ISpecialization::NoArgs),
+        description: "Return control to a debugger",
+    },
+    Metadata {
+        mnemonic: "csrrw",
+        format: Format::I(ISpecialization::Csr),
+        description: "t = CSRs[csr]; CSRs[csr] = rs1; rd = t",
+    },
+    Metadata {
+        mnemonic: "csrrs",
+        format: Format::I(ISpecialization::Csr),
+        description: "t = CSRs[csr]; CSRs[csr] = t | rs1; rd = t",
+    },
+    Metadata {
+        mnemonic: "csrrc",
+        format: Format::I(ISpecialization::Csr),
+        description: "t = CSRs[csr]; CSRs[csr] = t & ~rs1; rd = t",
+    },
+    Metadata {
+        mnemonic: "csrrwi",
+        format: Format::I(ISpecialization::Csri),
+        description: "rd = CSRs[csr]; CSRs[csr] = zext(uimm)",
+    },
+    Metadata {
+        mnemonic: "csrrsi",
+        format: Format::I(ISpecialization::Csri),
+        description: "t = CSRs[csr]; CSRs[csr] = t | zext(uimm); rd = t",
+    },
+    Metadata {
+        mnemonic: "csrrci",
+        format: Format::I(ISpecialization::Csri),
+        description: "t = CSRs[csr]; CSRs[csr] = t & ~zext(uimm); rd = t",
+    },
+    Metadata {
+        mnemonic: "mul",
+        format: Format::R,
+        description: "rd = (rs1 * rs2)[31:0]",
+    },
+    Metadata {
+        mnemonic: "mulh",
+        format: Format::R,
+        description: "rd = (sext(rs1) * sext(rs2))[63:32]",
+    },
+    Metadata {
+        mnemonic: "mulhsu",
+        format: Format::R,
+        description: "rd = (sext(rs1) * zext(rs2))[63:32]",
+    },
+    Metadata {
+        mnemonic: "mulhu",
+        format: Format::R,
+        description: "rd = (zext(rs1) * zext(rs2))[63:32]",
+    },
+    Metadata {
+        mnemonic: "div",
+        format: Format::R,
+        description: "rd = rs1 /s rs2",
+    },
+    Metadata {
+        mnemonic: "divu",
+        format: Format::R,
+        description: "rd = rs1 /u rs2",
+    },
+    Metadata {
+        mnemonic: "rem",
+        format: Format::R,
+        description: "rd = rs1 %s rs2",
+    },
+    Metadata {
+        mnemonic: "remu",
+        format: Format::R,
+        description: "rd = rs1 %u rs2",
+    },
+];
+
+pub fn metadata(mnemonic: &str) -> Option<&'static Metadata> {
+    INSTRUCTIONS
+        .iter()
+        .find(|m| m.mnemonic.eq_ignore_ascii_case(mnemonic))
+}
+
+pub fn mnemonics() -> impl Iterator<Item = &'static str> {
+    INSTRUCTIONS.iter().map(|m| m.mnemonic)
+}
+
+impl FromStr for Instruction {
+    type Err = Error;
+
+    fn from_str<'a>(mnemonic: &str) -> Result<Self, Error> {
+        let (name, args) = split_mnemonic(mnemonic);
+
+        match name.to_ascii_lowercase().as_str() {
+            "lui" => U::from_args(&args).map(Instruction::Lui),
+            "auipc" => U::from_args(&args).map(Instruction::Auipc),
+            "jal" => J::from_args(&args).map(Instruction::Jal),
+            "jalr" => I::from_args(&args).map(Instruction::Jalr),
+            "beq" => B::from_args(&args).map(Instruction::Beq),
+            "bne" => B::from_args(&args).map(Instruction::Bne),
+            "blt" => B::from_args(&args).map(Instruction::Blt),
+            "bge" => B::from_args(&args).map(Instruction::Bge),
+            "bltu" => B::from_args(&args).map(Instruction::Bltu),
+            "bgeu" => B::from_args(&args).map(Instruction::Bgeu),
+            "lb" => Load::from_args(&args).map(Instruction::Lb),
+            "lh" => Load::from_args(&args).map(Instruction::Lh),
+            "lw" => Load::from_args(&args).map(Instruction::Lw),
+            "lbu" => Load::from_args(&args).map(Instruction::Lbu),
+            "lhu" => Load::from_args(&args).map(Instruction::Lhu),
+            "sb" => S::from_args(&args).map(Instruction::Sb),
+            "sh" => S::from_args(&args).map(Instruction::Sh),
+            "sw" => S::from_args(&args).map(Instruction::Sw),
+            "addi" => I::from_args(&args).map(Instruction::Addi),
+            "slti" => I::from_args(&args).map(Instruction::Slti),
+            "sltiu" => I::from_args(&args).map(Instruction::Sltiu),
+            "xori" => I::from_args(&args).map(Instruction::Xori),
+            "ori" => I::from_args(&args).map(Instruction::Ori),
+            "andi" => I::from_args(&args).map(Instruction::Andi),
+            "slli" => Shift::from_args(&args).map(Instruction::Slli),
+            "srli" => Shift::from_args(&args).map(Instruction::Srli),
+            "srai" => Shift::from_args(&args).map(Instruction::Srai),
+            "add" => R::from_args(&args).map(Instruction::Add),
+            "sub" => R::from_args(&args).map(Instruction::Sub),
+            "sll" => R::from_args(&args).map(Instruction::Sll),
+            "slt" => R::from_args(&args).map(Instruction::Slt),
+            "sltu" => R::from_args(&args).map(Instruction::Sltu),
+            "xor" => R::from_args(&args).map(Instruction::Xor),
+            "srl" => R::from_args(&args).map(Instruction::Srl),
+            "sra" => R::from_args(&args).map(Instruction::Sra),
+            "or" => R::from_args(&args).map(Instruction::Or),
+            "and" => R::from_args(&args).map(Instruction::And),
+            "fence" => Fence::from_args(&args).map(Instruction::Fence),
+            "fence.i" => parse_no_args(&args).map(Instruction::FenceI),
+            "ecall" => parse_no_args(&args).map(Instruction::Ecall),
+            "ebreak" => parse_no_args(&args).map(Instruction::Ebreak),
+            "csrrw" => Csr::from_args(&args).map(Instruction::Csrrw),
+            "csrrs" => Csr::from_args(&args).map(Instruction::Csrrs),
+            "csrrc" => Csr::from_args(&args).map(Instruction::Csrrc),
+            "csrrwi" => Csri::from_args(&args).map(Instruction::Csrrwi),
+            "csrrsi" => Csri::from_args(&args).map(Instruction::Csrrsi),
+            "csrrci" => Csri::from_args(&args).map(Instruction::Csrrci),
+            // M extension
+            "mul" => R::from_args(&args).map(Instruction::Mul),
+            "mulh" => R::from_args(&args).map(Instruction::Mulh),
+            "mulhsu" => R::from_args(&args).map(Instruction::Mulhsu),
+            "mulhu" => R::from_args(&args).map(Instruction::Mulhu),
+            "div" => R::from_args(&args).map(Instruction::Div),
+            "divu" => R::from_args(&args).map(Instruction::Divu),
+            "rem" => R::from_args(&args).map(Instruction::Rem),
+            "remu" => R::from_args(&args).map(Instruction::Remu),
+            // Pseudo-instructions that are a single addi
+            "nop" => parse_no_args(&args).map(Instruction::Addi),
+            "li" => parse_li(&args).map(Instruction::Addi),
+            _ => Err(invalid_instruction_name(name, mnemonics())),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Lui(u) => write!(f, "lui {}", u),
+            Instruction::Auipc(u) => write!(f, "auipc {}", u),
+            Instruction::Jal(j) => write!(f, "jal {}", j),
+            Instruction::Jalr(i) => write!(f, "jalr {}", i),
+            Instruction::Beq(b) => write!(f, "beq {}", b),
+            Instruction::Bne(b) => write!(f, "bne {}", b),
+            Instruction::Blt(b) => write!(f, "blt {}", b),
+            Instruction::Bge(b) => write!(f, "bge {}", b),
+            Instruction::Bltu(b) => write!(f, "bltu {}", b),
+            Instruction::Bgeu(b) => write!(f, "bgeu {}", b),
+            Instruction::Lb(i) => write!(f, "lb {}", i),
+            Instruction::Lh(i) => write!(f, "lh {}", i),
+            Instruction::Lw(i) => write!(f, "lw {}", i),
+            Instruction::Lbu(i) => write!(f, "lbu {}", i),
+            Instruction::Lhu(i) => write!(f, "lhu {}", i),
+            Instruction::Sb(s) => write!(f, "sb {}", s),
+            Instruction::Sh(s) => write!(f, "sh {}", s),
+            Instruction::Sw(s) => write!(f, "sw {}", s),
+            Instruction::Addi(i) => write!(f, "addi {}", i),
+            Instruction::Slti(i) => write!(f, "slti {}", i),
+            Instruction::Sltiu(i) => write!(f, "sltiu {}", i),
+            Instruction::Xori(i) => write!(f, "xori {}", i),
+            Instruction::Ori(i) => write!(f, "ori {}", i),
+            Instruction::Andi(i) => write!(f, "andi {}", i),
+            Instruction::Slli(sh) => write!(f, "slli {}", sh),
+            Instruction::Srli(sh) => write!(f, "srli {}", sh),
+            Instruction::Srai(sh) => write!(f, "srai {}", sh),
+            Instruction::Add(r) => write!(f, "add {}", r),
+            Instruction::Sub(r) => write!(f, "sub {}", r),
+            Instruction::Sll(r) => write!(f, "sll {}", r),
+            Instruction::Slt(r) => write!(f, "slt {}", r),
+            Instruction::Sltu(r) => write!(f, "sltu {}", r),
+            Instruction::Xor(r) => write!(f, "xor {}", r),
+            Instruction::Srl(r) => write!(f, "srl {}", r),
+            Instruction::Sra(r) => write!(f, "sra {}", r),
+            Instruction::Or(r) => write!(f, "or {}", r),
+            Instruction::And(r) => write!(f, "and {}", r),
+            Instruction::Fence(i) => write!(f, "fence {}", i),
+            Instruction::FenceI(_) => write!(f, "fence.i"),
+            Instruction::Ecall(_) => write!(f, "ecall"),
+            Instruction::Ebreak(_) => write!(f, "ebreak"),
+            Instruction::Csrrw(csr) => write!(f, "csrrw {}", csr),
+            Instruction::Csrrs(csr) => write!(f, "csrrs {}", csr),
+            Instruction::Csrrc(csr) => write!(f, "csrrc {}", csr),
+            Instruction::Csrrwi(csri) => write!(f, "csrrwi {}", csri),
+            Instruction::Csrrsi(csri) => write!(f, "csrrsi {}", csri),
+            Instruction::Csrrci(csri) => write!(f, "csrrci {}", csri),
+            Instruction::Mul(r) => write!(f, "mul {}", r),
+            Instruction::Mulh(r) => write!(f, "mulh {}", r),
+            Instruction::Mulhsu(r) => write!(f, "mulhsu {}", r),
+            Instruction::Mulhu(r) => write!(f, "mulhu {}", r),
+            Instruction::Div(r) => write!(f, "div {}", r),
+            Instruction::Divu(r) => write!(f, "divu {}", r),
+            Instruction::Rem(r) => write!(f, "rem {}", r),
+            Instruction::Remu(r) => write!(f, "remu {}", r),
+        }
+    }
+}
+
+impl Instruction {
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            Instruction::Lui(u) => u.to_u32(&Opcode::from_u32(0b0110111).unwrap()),
+            Instruction::Auipc(u) => u.to_u32(&Opcode::from_u32(0b0010111).unwrap()),
+            Instruction::Jal(j) => j.to_u32(&Opcode::from_u32(0b1101111).unwrap()),
+            Instruction::Jalr(i) => i.to_u32(
+                &Opcode::from_u32(0b1100111).unwrap(),
+                &Funct3::from_u32(0b000).unwrap(),
+            ),
+            Instruction::Beq(b) => b.to_u32(
+                &Opcode::from_u32(0b1100011).unwrap(),
+                &Funct3::from_u32(0b000).unwrap(),
+            ),
+            Instruction::Bne(b) => b.to_u32(
+                &Opcode::from_u32(0b1100011).unwrap(),
+                &Funct3::from_u32(0b001).unwrap(),
+            ),
+            Instruction::Blt(b) => b.to_u32(
+                &Opcode::from_u32(0b1100011).unwrap(),
+                &Funct3::from_u32(0b100).unwrap(),
+            ),
+            Instruction::Bge(b) => b.to_u32(
+                &Opcode::from_u32(0b1100011).unwrap(),
+                &Funct3::from_u32(0b101).unwrap(),
+            ),
+            Instruction::Bltu(b) => b.to_u32(
+                &Opcode::from_u32(0b1100011).unwrap(),
+                &Funct3::from_u32(0b110).unwrap(),
+            ),
+            Instruction::Bgeu(b) => b.to_u32(
+                &Opcode::from_u32(0b1100011).unwrap(),
+                &Funct3::from_u32(0b111).unwrap(),
+            ),
+            Instruction::Lb(i) => i.to_u32(
+                &Opcode::from_u32(0b0000011).unwrap(),
+                &Funct3::from_u32(0b000).unwrap(),
+            ),
+            Instruction::Lh(i) => i.to_u32(
+                &Opcode::from_u32(0b0000011).unwrap(),
+                &Funct3::from_u32(0b001).unwrap(),
+            ),
+            Instruction::Lw(i) => i.to_u32(
+                &Opcode::from_u32(0b0000011).unwrap(),
+                &Funct3::from_u32(0b010).unwrap(),
+            ),
+            Instruction::Lbu(i) => i.to_u32(
+                &Opcode::from_u32(0b0000011).unwrap(),
+                &Funct3::from_u32(0b100).unwrap(),
+            ),
+            Instruction::Lhu(i) => i.to_u32(
+                &Opcode::from_u32(0b0000011).unwrap(),
+                &Funct3::from_u32(0b101).unwrap(),
+            ),
+            Instruction::Sb(s) => s.to_u32(
+                &Opcode::from_u32(0b0100011).unwrap(),
+                &Funct3::from_u32(0b000).unwrap(),
+            ),
+            Instruction::Sh(s) => s.to_u32(
+                &Opcode::from_u32(0b0100011).unwrap(),
+                &Funct3::from_u32(0b001).unwrap(),
+            ),
+            Instruction::Sw(s) => s.to_u32(
+                &Opcode::from_u32(0b0100011).unwrap(),
+                &Funct3::from_u32(0b010).unwrap(),
+            ),
+            Instruction::Addi(i) => i.to_u32(
+                &Opcode::from_u32(0b0010011).unwrap(),
+                &Funct3::from_u32(0b000).unwrap(),
+            ),
+            Instruction::Slti(i) => i.to_u32(
+                &Opcode::from_u32(0b0010011).unwrap(),
+                &Funct3::from_u32(0b010).unwrap(),
+            ),
+            Instruction::Sltiu(i) => i.to_u32(
+                &Opcode::from_u32(0b0010011).unwrap(),
+                &Funct3::from_u32(0b011).unwrap(),
+            ),
+            Instruction::Xori(i) => i.to_u32(
+                &Opcode::from_u32(0b0010011).unwrap(),
+                &Funct3::from_u32(0b100).unwrap(),
+            ),
+            Instruction::Ori(i) => i.to_u32(
+                &Opcode::from_u32(0b0010011).unwrap(),
+                &Funct3::from_u32(0b110).unwrap(),
+            ),
+            Instruction::Andi(i) => i.to_u32(
+                &Opcode::from_u32(0b0010011).unwrap(),
+                &Funct3::from_u32(0b111).unwrap(),
+            ),
+            Instruction::Slli(sh) => sh.to_u32(
+                &Opcode::from_u32(0b0010011).unwrap(),
+                &Funct3::from_u32(0b001).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::Srli(sh) => sh.to_u32(
+                &Opcode::from_u32(0b0010011).unwrap(),
+                &Funct3::from_u32(0b101).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::Srai(sh) => sh.to_u32(
+                &Opcode::from_u32(0b0010011).unwrap(),
+                &Funct3::from_u32(0b101).unwrap(),
+                &Funct7::from_u32(0b0100000).unwrap(),
+            ),
+            Instruction::Add(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b000).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::Sub(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b000).unwrap(),
+                &Funct7::from_u32(0b0100000).unwrap(),
+            ),
+            Instruction::Sll(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b001).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::Slt(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b010).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::Sltu(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b011).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::Xor(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b100).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::Srl(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b101).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::Sra(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b101).unwrap(),
+                &Funct7::from_u32(0b0100000).unwrap(),
+            ),
+            Instruction::Or(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b110).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::And(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b111).unwrap(),
+                &Funct7::from_u32(0b0000000).unwrap(),
+            ),
+            Instruction::Fence(f) => f.to_u32(
+                &Opcode::from_u32(0b0001111).unwrap(),
+                &Funct3::from_u32(0b000).unwrap(),
+            ),
+            Instruction::FenceI(i) => i.to_u32(
+                &Opcode::from_u32(0b0001111).unwrap(),
+                &Funct3::from_u32(0b001).unwrap(),
+            ),
+            Instruction::Ecall(_) => 0b000000000000_00000_000_00000_1110011,
+            Instruction::Ebreak(_) => 0b000000000001_00000_000_00000_1110011,
+            Instruction::Csrrw(csr) => csr.to_u32(
+                &Opcode::from_u32(0b1110011).unwrap(),
+                &Funct3::from_u32(0b001).unwrap(),
+            ),
+            Instruction::Csrrs(csr) => csr.to_u32(
+                &Opcode::from_u32(0b1110011).unwrap(),
+                &Funct3::from_u32(0b010).unwrap(),
+            ),
+            Instruction::Csrrc(csr) => csr.to_u32(
+                &Opcode::from_u32(0b1110011).unwrap(),
+                &Funct3::from_u32(0b011).unwrap(),
+            ),
+            Instruction::Csrrwi(csri) => csri.to_u32(
+                &Opcode::from_u32(0b1110011).unwrap(),
+                &Funct3::from_u32(0b101).unwrap(),
+            ),
+            Instruction::Csrrsi(csri) => csri.to_u32(
+                &Opcode::from_u32(0b1110011).unwrap(),
+                &Funct3::from_u32(0b110).unwrap(),
+            ),
+            Instruction::Csrrci(csri) => csri.to_u32(
+                &Opcode::from_u32(0b1110011).unwrap(),
+                &Funct3::from_u32(0b111).unwrap(),
+            ),
+            Instruction::Mul(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b000).unwrap(),
+                &Funct7::from_u32(0b0000001).unwrap(),
+            ),
+            Instruction::Mulh(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b001).unwrap(),
+                &Funct7::from_u32(0b0000001).unwrap(),
+            ),
+            Instruction::Mulhsu(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b010).unwrap(),
+                &Funct7::from_u32(0b0000001).unwrap(),
+            ),
+            Instruction::Mulhu(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b011).unwrap(),
+                &Funct7::from_u32(0b0000001).unwrap(),
+            ),
+            Instruction::Div(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b100).unwrap(),
+                &Funct7::from_u32(0b0000001).unwrap(),
+            ),
+            Instruction::Divu(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b101).unwrap(),
+                &Funct7::from_u32(0b0000001).unwrap(),
+            ),
+            Instruction::Rem(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b110).unwrap(),
+                &Funct7::from_u32(0b0000001).unwrap(),
+            ),
+            Instruction::Remu(r) => r.to_u32(
+                &Opcode::from_u32(0b0110011).unwrap(),
+                &Funct3::from_u32(0b111).unwrap(),
+                &Funct7::from_u32(0b0000001).unwrap(),
+            ),
+        }
+    }
+
+    pub fn from_u32(inst: u32) -> Result<Self, DecodeError> {
+        let opcode = Opcode::extract(inst).to_u32();
+        let funct3 = Funct3::extract(inst).to_u32();
+        let funct7 = Funct7::extract(inst).to_u32();
+
+        let illegal_funct3 = || DecodeError::IllegalFunct3 { opcode, funct3 };
+        let illegal_funct7 = || DecodeError::IllegalFunct7 {
+            opcode,
+            funct3,
+            funct7,
+        };
+
+        match opcode {
+            0b0110111 => Ok(Instruction::Lui(U::from_u32(inst))),
+            0b0010111 => Ok(Instruction::Auipc(U::from_u32(inst))),
+            0b1101111 => Ok(Instruction::Jal(J::from_u32(inst))),
+            0b1100111 => match funct3 {
+                0b000 => Ok(Instruction::Jalr(I::from_u32(inst))),
+                _ => Err(illegal_funct3()),
+            },
+            0b1100011 => match funct3 {
+                0b000 => Ok(Instruction::Beq(B::from_u32(inst))),
+                0b001 => Ok(Instruction::Bne(B::from_u32(inst))),
+                0b100 => Ok(Instruction::Blt(B::from_u32(inst))),
+                0b101 => Ok(Instruction::Bge(B::from_u32(inst))),
+                0b110 => Ok(Instruction::Bltu(B::from_u32(inst))),
+                0b111 => Ok(Instruction::Bgeu(B::from_u32(inst))),
+                _ => Err(illegal_funct3()),
+            },
+            0b0000011 => match funct3 {
+                0b000 => Ok(Instruction::Lb(Load::from_u32(inst))),
+                0b001 => Ok(Instruction::Lh(Load::from_u32(inst))),
+                0b010 => Ok(Instruction::Lw(Load::from_u32(inst))),
+                0b100 => Ok(Instruction::Lbu(Load::from_u32(inst))),
+                0b101 => Ok(Instruction::Lhu(Load::from_u32(inst))),
+                _ => Err(illegal_funct3()),
+            },
+            0b0100011 => match funct3 {
+                0b000 => Ok(Instruction::Sb(S::from_u32(inst))),
+                0b001 => Ok(Instruction::Sh(S::from_u32(inst))),
+                0b010 => Ok(Instruction::Sw(S::from_u32(inst))),
+                _ => Err(illegal_funct3()),
+            },
+            0b0010011 => match (funct3, funct7) {
+                (0b000, _) => Ok(Instruction::Addi(I::from_u32(inst))),
+                (0b010, _) => Ok(Instruction::Slti(I::from_u32(inst))),
+                (0b011, _) => Ok(Instruction::Sltiu(I::from_u32(inst))),
+                (0b100, _) => Ok(Instruction::Xori(I::from_u32(inst))),
+                (0b110, _) => Ok(Instruction::Ori(I::from_u32(inst))),
+                (0b111, _) => Ok(Instruction::Andi(I::from_u32(inst))),
+                (0b001, 0b0000000) => Ok(Instruction::Slli(Shift::from_u32(inst))),
+                (0b101, 0b0000000) => Ok(Instruction::Srli(Shift::from_u32(inst))),
+                (0b101, 0b0100000) => Ok(Instruction::Srai(Shift::from_u32(inst))),
+                _ => Err(illegal_funct7()),
+            },
+            0b0110011 => match (funct3, funct7) {
+                (0b000, 0b0000000) => Ok(Instruction::Add(R::from_u32(inst))),
+                (0b000, 0b0100000) => Ok(Instruction::Sub(R::from_u32(inst))),
+                (0b001, 0b0000000) => Ok(Instruction::Sll(R::from_u32(inst))),
+                (0b010, 0b0000000) => Ok(Instruction::Slt(R::from_u32(inst))),
+                (0b011, 0b0000000) => Ok(Instruction::Sltu(R::from_u32(inst))),
+                (0b100, 0b0000000) => Ok(Instruction::Xor(R::from_u32(inst))),
+                (0b101, 0b0000000) => Ok(Instruction::Srl(R::from_u32(inst))),
+                (0b101, 0b0100000) => Ok(Instruction::Sra(R::from_u32(inst))),
+                (0b110, 0b0000000) => Ok(Instruction::Or(R::from_u32(inst))),
+                (0b111, 0b0000000) => Ok(Instruction::And(R::from_u32(inst))),
+                (0b000, 0b0000001) => Ok(Instruction::Mul(R::from_u32(inst))),
+                (0b001, 0b0000001) => Ok(Instruction::Mulh(R::from_u32(inst))),
+                (0b010, 0b0000001) => Ok(Instruction::Mulhsu(R::from_u32(inst))),
+                (0b011, 0b0000001) => Ok(Instruction::Mulhu(R::from_u32(inst))),
+                (0b100, 0b0000001) => Ok(Instruction::Div(R::from_u32(inst))),
+                (0b101, 0b0000001) => Ok(Instruction::Divu(R::from_u32(inst))),
+                (0b110, 0b0000001) => Ok(Instruction::Rem(R::from_u32(inst))),
+                (0b111, 0b0000001) => Ok(Instruction::Remu(R::from_u32(inst))),
+                _ => Err(illegal_funct7()),
+            },
+            0b0001111 => match funct3 {
+                0b000 => Fence::from_u32(inst).map(Instruction::Fence),
+                0b001 => Ok(Instruction::FenceI(I::from_u32(inst))),
+                _ => Err(illegal_funct3()),
+            },
+            0b1110011 => match funct3 {
+                0b000 => {
+                    // ecall and ebreak are encoded as constants, so rd and rs1
+                    // must be zero for the word to round trip.
+                    let reserved = inst & (Register::<Rd>::MASK | Register::<Rs1>::MASK);
+                    if reserved != 0 {
+                        return Err(DecodeError::ReservedBitsSet { mask: reserved });
+                    }
+                    match inst >> 20 {
+                        0 => Ok(Instruction::Ecall(I::from_u32(inst))),
+                        1 => Ok(Instruction::Ebreak(I::from_u32(inst))),
+                        funct12 => Err(DecodeError::IllegalFunct12 { funct12 }),
+                    }
+                }
+                0b001 => Ok(Instruction::Csrrw(Csr::from_u32(inst))),
+                0b010 => Ok(Instruction::Csrrs(Csr::from_u32(inst))),
+                0b011 => Ok(Instruction::Csrrc(Csr::from_u32(inst))),
+                0b101 => Ok(Instruction::Csrrwi(Csri::from_u32(inst))),
+                0b110 => Ok(Instruction::Csrrsi(Csri::from_u32(inst))),
+                0b111 => Ok(Instruction::Csrrci(Csri::from_u32(inst))),
+                _ => Err(illegal_funct3()),
+            },
+            _ => Err(DecodeError::IllegalOpcode(opcode)),
+        }
+    }
+
+    pub fn to_format(&self) -> Format {
+        match self {
+            Instruction::Lui(_) => Format::U,
+            Instruction::Auipc(_) => Format::U,
+            Instruction::Jal(_) => Format::J,
+            Instruction::Jalr(_) => Format::I(ISpecialization::None),
+            Instruction::Beq(_) => Format::B,
+            Instruction::Bne(_) => Format::B,
+            Instruction::Blt(_) => Format::B,
+            Instruction::Bge(_) => Format::B,
+            Instruction::Bltu(_) => Format::B,
+            Instruction::Bgeu(_) => Format::B,
+            Instruction::Lb(_) => Format::I(ISpecialization::Load),
+            Instruction::Lh(_) => Format::I(ISpecialization::Load),
+            Instruction::Lw(_) => Format::I(ISpecialization::Load),
+            Instruction::Lbu(_) => Format::I(ISpecialization::Load),
+            Instruction::Lhu(_) => Format::I(ISpecialization::Load),
+            Instruction::Sb(_) => Format::S,
+            Instruction::Sh(_) => Format::S,
+            Instruction::Sw(_) => Format::S,
+            Instruction::Addi(_) => Format::I(ISpecialization::None),
+            Instruction::Slti(_) => Format::I(ISpecialization::None),
+            Instruction::Sltiu(_) => Format::I(ISpecialization::None),
+            Instruction::Xori(_) => Format::I(ISpecialization::None),
+            Instruction::Ori(_) => Format::I(ISpecialization::None),
+            Instruction::Andi(_) => Format::I(ISpecialization::None),
+            Instruction::Slli(_) => Format::Shift,
+            Instruction::Srli(_) => Format::Shift,
+            Instruction::Srai(_) => Format::Shift,
+            Instruction::Add(_) => Format::R,
+            Instruction::Sub(_) => Format::R,
+            Instruction::Sll(_) => Format::R,
+            Instruction::Slt(_) => Format::R,
+            Instruction::Sltu(_) => Format::R,
+            Instruction::Xor(_) => Format::R,
+            Instruction::Srl(_) => Format::R,
+            Instruction::Sra(_) => Format::R,
+            Instruction::Or(_) => Format::R,
+            Instruction::And(_) => Format::R,
+            Instruction::Fence(_) => Format::I(ISpecialization::Fence),
+            Instruction::FenceI(_) => Format::I(ISpecialization::NoArgs),
+            Instruction::Ecall(_) => Format::I(ISpecialization::NoArgs),
+            Instruction::Ebreak(_) => Format::I(ISpecialization::NoArgs),
+            Instruction::Csrrw(_) => Format::I(ISpecialization::Csr),
+            Instruction::Csrrs(_) => Format::I(ISpecialization::Csr),
+            Instruction::Csrrc(_) => Format::I(ISpecialization::Csr),
+            Instruction::Csrrwi(_) => Format::I(ISpecialization::Csri),
+            Instruction::Csrrsi(_) => Format::I(ISpecialization::Csri),
+            Instruction::Csrrci(_) => Format::I(ISpecialization::Csri),
+            Instruction::Mul(_) => Format::R,
+            Instruction::Mulh(_) => Format::R,
+            Instruction::Mulhsu(_) => Format::R,
+            Instruction::Mulhu(_) => Format::R,
+            Instruction::Div(_) => Format::R,
+            Instruction::Divu(_) => Format::R,
+            Instruction::Rem(_) => Format::R,
+            Instruction::Remu(_) => Format::R,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::discriminant;
+
+    const EVERY_VARIANT: &[&str] = &[
+        "lui a0, 0xFFFFF",
+        "auipc t0, 0x12345",
+        "jal ra, -1048576",
+        "jalr ra, sp, -2048",
+        "beq a0, a1, -4096",
+        "bne a0, a1, 4094",
+        "blt s0, s11, -2",
+        "bge t6, zero, 2",
+        "bltu x1, x2, 1000",
+        "bgeu x30, x31, -1000",
+        "lb a0, -1(sp)",
+        "lh a0, 2047(sp)",
+        "lw a0, (sp)",
+        "lbu a0, -2048(a1)",
+        "lhu a0, 16(gp)",
+        "sb a0, -1(sp)",
+        "sh t0, 2047(tp)",
+        "sw s1, -2048(s2)",
+        "addi a0, a0, -1",
+        "slti a0, a1, 2047",
+        "sltiu a0, a1, -2048",
+        "xori a0, a1, 0x7FF",
+        "ori a0, a1, 0b101",
+        "andi a0, a1, 255",
+        "slli a0, a1, 31",
+        "srli a0, a1, 1",
+        "srai a0, a1, 17",
+        "add t0, a0, a1",
+        "sub t0, a0, a1",
+        "sll t0, a0, a1",
+        "slt t0, a0, a1",
+        "sltu t0, a0, a1",
+        "xor t0, a0, a1",
+        "srl t0, a0, a1",
+        "sra t0, a0, a1",
+        "or t0, a0, a1",
+        "and t0, a0, a1",
+        "fence",
+        "fence ir, ow",
+        "fence.i",
+        "ecall",
+        "ebreak",
+        "csrrw a0, cycle, a1",
+        "csrrs a0, 0xFFF, a1",
+        "csrrc a0, instreth, zero",
+        "csrrwi a0, time, 31",
+        "csrrsi a0, 0x340, 1",
+        "csrrci a0, cycleh, 0",
+        "mul a0, a1, a2",
+        "mulh a0, a1, a2",
+        "mulhsu a0, a1, a2",
+        "mulhu a0, a1, a2",
+        "div a0, a1, a2",
+        "divu a0, a1, a2",
+        "rem a0, a1, a2",
+        "remu a0, a1, a2",
+    ];
+
+    #[test]
+    fn encode_m_extension() {
+        let expected = [
+            ("mul a0, a1, a2", 0x02C58533),
+            ("mulh t0, t1, t2", 0x027312B3),
+            ("mulhsu s0, s1, zero", 0x0204A433),
+            ("mulhu ra, sp, x31", 0x03F130B3),
+            ("div x31, a0, sp", 0x02254FB3),
+            ("divu a2, a1, a0", 0x02A5D633),
+            ("rem t2, s0, t0", 0x025463B3),
+            ("remu a0, a0, a0", 0x02A57533),
+        ];
+
+        for (mnemonic, word) in expected.iter() {
+            let instruction = Instruction::from_str(mnemonic).unwrap();
+            assert_eq!(instruction.to_u32(), *word, "{}", mnemonic);
+            match instruction.to_format() {
+                Format::R => {}
+                other => panic!("{}: {:?}", mnemonic, other),
+            }
+            assert_eq!(
+                Instruction::from_str(&instruction.to_string())
+                    .unwrap()
+                    .to_u32(),
+                *word,
+                "{}",
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    fn nop_and_li_are_addi() {
+        let word = |mnemonic| Instruction::from_str(mnemonic).unwrap().to_u32();
+        assert_eq!(word("nop"), 0x0000_0013);
+        assert_eq!(word("li a0, -5"), word("addi a0, zero, -5"));
+        assert!(Instruction::from_str("li a0, 0x12345").is_err());
+    }
+
+    #[test]
+    fn metadata_matches_every_variant() {
+        for metadata in INSTRUCTIONS {
+            assert!(EVERY_VARIANT
+                .iter()
+                .any(|mnemonic| split_mnemonic(mnemonic).0 == metadata.mnemonic));
+        }
+        for mnemonic in EVERY_VARIANT {
+            let (name, _) = split_mnemonic(mnemonic);
+            let instruction = Instruction::from_str(mnemonic).unwrap();
+            let metadata = metadata(name).unwrap_or_else(|| panic!("{}", name));
+            assert_eq!(metadata.format, instruction.to_format(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn immediate_ranges_match_operands() {
+        for metadata in INSTRUCTIONS {
+            for immediate in metadata.format.immediates() {
+                assert!(
+                    metadata.format.operands().contains(immediate.operand),
+                    "{}: {}",
+                    metadata.mnemonic,
+                    immediate.operand
+                );
+            }
+        }
+        assert_eq!(
+            Format::B.immediates()[0].to_string(),
+            "offset[12:1]: 13-bit signed range [-4096, 4095], even"
+        );
+    }
+
+    #[test]
+    fn decode_every_variant() {
+        for mnemonic in EVERY_VARIANT {
+            let instruction = Instruction::from_str(mnemonic).unwrap();
+            let word = instruction.to_u32();
+            let decoded = Instruction::from_u32(word)
+                .unwrap_or_else(|e| panic!("{} (0x{:08X}): {:?}", mnemonic, word, e));
+
+            assert_eq!(
+                discriminant(&instruction),
+                discriminant(&decoded),
+                "{}",
+                mnemonic
+            );
+            assert_eq!(decoded.to_u32(), word, "{}", mnemonic);
+        }
+    }
+
+    #[test]
+    fn decode_round_trips_arbitrary_words() {
+        // xorshift32, so that the words checked are the same on every run.
+        let mut state = 0x2545_F491_u32;
+        let mut decoded = 0;
+
+        for _ in 0..1_000_000 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            // Force the low bits to 0b11 so that most words are not
+            // rejected for being compressed instructions.
+            let word = state | 0b11;
+
+            if let Ok(instruction) = Instruction::from_u32(word) {
+                assert_eq!(instruction.to_u32(), word, "{}", instruction);
+                decoded += 1;
+            }
+        }
+        assert!(decoded > 0);
+    }
+
+    #[test]
+    fn decode_illegal_words() {
+        match Instruction::from_u32(0x0000_0000) {
+            Err(DecodeError::IllegalOpcode(0)) => {}
+            other => panic!("{:?}", other),
+        }
+        match Instruction::from_u32(0x0000_2067) {
+            Err(DecodeError::IllegalFunct3 {
+                opcode: 0b1100111,
+                funct3: 0b010,
+            }) => {}
+            other => panic!("{:?}", other),
+        }
+        match Instruction::from_u32(0x0400_0033) {
+            Err(DecodeError::IllegalFunct7 {
+                opcode: 0b0110011,
+                funct3: 0b000,
+                funct7: 0b0000010,
+            }) => {}
+            other => panic!("{:?}", other),
+        }
+        match Instruction::from_u32(0x3020_0073) {
+            Err(DecodeError::IllegalFunct12 { funct12: 0x302 }) => {}
+            other => panic!("{:?}", other),
+        }
+        match Instruction::from_u32(0x0FF0_008F) {
+            Err(DecodeError::ReservedBitsSet { mask: 0x80 }) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn error_spans_and_messages() {
+        let cases = [
+            (
+                "addd a0, a1, a2",
+                (0, 4),
+                "unknown instruction `addd`, did you mean `add`?",
+            ),
+            ("frob a0", (0, 4), "unknown instruction `frob`"),
+            ("add a0, qq, a2", (8, 10), "`qq` is not a register"),
+            (
+                "add a0, a1, x32",
+                (12, 15),
+                "`x32` is outside the range x0..x31",
+            ),
+            (
+                "add a0, a9, a2",
+                (8, 10),
+                "`a9` is outside the range a0..a7",
+            ),
+            (
+                "addi t0, t0, 5000",
+                (13, 17),
+                "immediate 5000 exceeds 12-bit signed range [-2048, 2047]",
+            ),
+            (
+                "lui t0, -1",
+                (8, 10),
+                "immediate -1 is below 20-bit unsigned range [0, 1048575]",
+            ),
+            ("lw a0, 4(sp", (11, 11), "missing `)`"),
+            ("lw a0, 4(sp)x", (12, 13), "unexpected text after `)`"),
+            (
+                "lw a0, 4(spp)",
+                (9, 12),
+                "`spp` is not a register, did you mean `sp`?",
+            ),
+            ("beq a0, a1, 3", (12, 13), "immediate must be even"),
+            ("add a0, a1", (10, 10), "expected 3 argument(s), found 2"),
+            ("ecall a0", (6, 8), "expected 0 argument(s), found 1"),
+        ];
+
+        for (input, (start, end), message) in cases.iter() {
+            let error = Instruction::from_str(input).unwrap_err();
+            assert_eq!(error.span(input), Span::new(*start, *end), "{}", input);
+            assert_eq!(error.to_string(), *message, "{}", input);
+        }
+    }
+}
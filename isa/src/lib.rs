@@ -0,0 +1,14 @@
+/* The RISC-V instructions understood by narvie, how to parse, print, encode
+ * and decode them, and the protocol for running them on narvie. Used by
+ * `narvie-cli` and available to other tools that talk to narvie.
+ */
+
+pub mod compressed;
+pub mod device;
+pub mod diagnostic;
+pub mod immediate;
+pub mod instruction;
+pub mod pseudo;
+pub mod random;
+pub mod register;
+pub mod register_file;
@@ -0,0 +1,475 @@
+use crate::immediate::{self, Immediate};
+use crate::instruction::{self, parse_help, parse_immediate, parse_register, split_mnemonic};
+use crate::instruction::{Csr, Csri, Error, Instruction, B, I, J, R, U};
+use crate::register::{Rd, Register, Rs1, Rs2};
+
+fn ra<T>() -> Register<T> {
+    Register::from_u32(1).unwrap()
+}
+
+fn t1<T>() -> Register<T> {
+    Register::from_u32(6).unwrap()
+}
+
+fn zero_offset() -> Immediate<immediate::I> {
+    Immediate::from_i32(0).unwrap()
+}
+
+/* Splits a 32 bit value into an upper 20 bits and a lower 12 bits such that
+ * `(upper << 12) + sext(lower) == value`, as used by `lui` and `auipc` pairs.
+ */
+fn split_word(value: i32) -> (Immediate<immediate::U>, Immediate<immediate::I>) {
+    let upper = (value.wrapping_add(0x800) as u32) >> 12;
+    let lower = value.wrapping_sub((upper << 12) as i32);
+    (
+        Immediate::from_i32(upper as i32).unwrap(),
+        Immediate::from_i32(lower).unwrap(),
+    )
+}
+
+fn load_immediate(rd: Register<Rd>, value: Immediate<immediate::Word>) -> Vec<Instruction> {
+    let value = value.to_i32();
+    if let Ok(imm) = Immediate::from_i32(value) {
+        vec![Instruction::Addi(I::new(rd, Register::ZERO, imm))]
+    } else {
+        let (upper, lower) = split_word(value);
+        let mut sequence = vec![Instruction::Lui(U::new(rd.to_role(), upper))];
+        if lower.to_i32() != 0 {
+            sequence.push(Instruction::Addi(I::new(rd.to_role(), rd.to_role(), lower)));
+        }
+        sequence
+    }
+}
+
+fn far_jump(link: Register<Rd>, scratch: Register<Rd>, offset: i32) -> Vec<Instruction> {
+    let (upper, lower) = split_word(offset);
+    vec![
+        Instruction::Auipc(U::new(scratch.to_role(), upper)),
+        Instruction::Jalr(I::new(link, scratch.to_role(), lower)),
+    ]
+}
+
+fn csr_read(rd: Register<Rd>, csr: i32) -> Vec<Instruction> {
+    vec![Instruction::Csrrs(Csr::new(
+        rd,
+        Register::ZERO,
+        Immediate::from_i32(csr).unwrap(),
+    ))]
+}
+
+type Sequence = Result<Vec<Instruction>, Error>;
+
+fn parse_unary(args: &[&str]) -> Result<(Register<Rd>, Register<Rs1>), Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            Some(|rd, rs| Ok((parse_register(0, rd)?, parse_register(1, rs)?))),
+            None,
+        ),
+    )
+}
+
+fn parse_branch_zero(args: &[&str]) -> Result<(Register<Rs1>, Immediate<immediate::B>), Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            Some(|rs, offset| Ok((parse_register(0, rs)?, parse_immediate(1, offset)?))),
+            None,
+        ),
+    )
+}
+
+/* Branches whose operands are swapped, `bgt rs, rt, offset` is
+ * `blt rt, rs, offset`.
+ */
+fn parse_swapped_branch(args: &[&str]) -> Result<B, Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            None,
+            Some(|rs, rt, offset| {
+                let rs: Register<Rs2> = parse_register(0, rs)?;
+                let rt: Register<Rs1> = parse_register(1, rt)?;
+                Ok(B::new(rt, rs, parse_immediate(2, offset)?))
+            }),
+        ),
+    )
+}
+
+fn parse_rd(args: &[&str]) -> Result<Register<Rd>, Error> {
+    parse_help(args, (None, Some(|rd| parse_register(0, rd)), None, None))
+}
+
+fn parse_rs(args: &[&str]) -> Result<Register<Rs1>, Error> {
+    parse_help(args, (None, Some(|rs| parse_register(0, rs)), None, None))
+}
+
+fn parse_jump_offset(args: &[&str]) -> Result<Immediate<immediate::J>, Error> {
+    parse_help(
+        args,
+        (None, Some(|offset| parse_immediate(0, offset)), None, None),
+    )
+}
+
+fn parse_far_offset(args: &[&str]) -> Result<Immediate<immediate::Word>, Error> {
+    parse_help(
+        args,
+        (None, Some(|offset| parse_immediate(0, offset)), None, None),
+    )
+}
+
+fn parse_csr_write(args: &[&str]) -> Result<Csr, Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            Some(|csr, rs| {
+                Ok(Csr::new(
+                    Register::ZERO,
+                    parse_register(1, rs)?,
+                    parse_immediate(0, csr)?,
+                ))
+            }),
+            None,
+        ),
+    )
+}
+
+fn parse_csr_write_immediate(args: &[&str]) -> Result<Csri, Error> {
+    parse_help(
+        args,
+        (
+            None,
+            None,
+            Some(|csr, imm| {
+                Ok(Csri::new(
+                    Register::ZERO,
+                    parse_immediate(1, imm)?,
+                    parse_immediate(0, csr)?,
+                ))
+            }),
+            None,
+        ),
+    )
+}
+
+/* Expands one of the pseudo-instructions listed in the RISC-V specification
+ * into the instructions that implement it.
+ *
+ * Returns `None` if `mnemonic` is not a pseudo-instruction, in which case it
+ * should be parsed as a single `Instruction`.
+ */
+pub fn expand(mnemonic: &str) -> Option<Sequence> {
+    let (name, args) = split_mnemonic(mnemonic);
+
+    let sequence = match name.to_ascii_lowercase().as_str() {
+        "nop" => parse_help(
+            &args,
+            (
+                Some(|| {
+                    Ok(vec![Instruction::Addi(I::new(
+                        Register::ZERO,
+                        Register::ZERO,
+                        zero_offset(),
+                    ))])
+                }),
+                None,
+                None,
+                None,
+            ),
+        ),
+        "li" => parse_help(
+            &args,
+            (
+                None,
+                None,
+                Some(|rd, imm| {
+                    Ok(load_immediate(
+                        parse_register(0, rd)?,
+                        parse_immediate(1, imm)?,
+                    ))
+                }),
+                None,
+            ),
+        ),
+        "mv" => parse_unary(&args)
+            .map(|(rd, rs)| vec![Instruction::Addi(I::new(rd, rs, zero_offset()))]),
+        "not" => parse_unary(&args).map(|(rd, rs)| {
+            vec![Instruction::Xori(I::new(
+                rd,
+                rs,
+                Immediate::from_i32(-1).unwrap(),
+            ))]
+        }),
+        "neg" => parse_unary(&args)
+            .map(|(rd, rs)| vec![Instruction::Sub(R::new(rd, Register::ZERO, rs.to_role()))]),
+        "seqz" => parse_unary(&args).map(|(rd, rs)| {
+            vec![Instruction::Sltiu(I::new(
+                rd,
+                rs,
+                Immediate::from_i32(1).unwrap(),
+            ))]
+        }),
+        "snez" => parse_unary(&args)
+            .map(|(rd, rs)| vec![Instruction::Sltu(R::new(rd, Register::ZERO, rs.to_role()))]),
+        "sltz" => parse_unary(&args)
+            .map(|(rd, rs)| vec![Instruction::Slt(R::new(rd, rs, Register::ZERO))]),
+        "sgtz" => parse_unary(&args)
+            .map(|(rd, rs)| vec![Instruction::Slt(R::new(rd, Register::ZERO, rs.to_role()))]),
+        "beqz" => parse_branch_zero(&args)
+            .map(|(rs, offset)| vec![Instruction::Beq(B::new(rs, Register::ZERO, offset))]),
+        "bnez" => parse_branch_zero(&args)
+            .map(|(rs, offset)| vec![Instruction::Bne(B::new(rs, Register::ZERO, offset))]),
+        "blez" => parse_branch_zero(&args).map(|(rs, offset)| {
+            vec![Instruction::Bge(B::new(
+                Register::ZERO,
+                rs.to_role(),
+                offset,
+            ))]
+        }),
+        "bgez" => parse_branch_zero(&args)
+            .map(|(rs, offset)| vec![Instruction::Bge(B::new(rs, Register::ZERO, offset))]),
+        "bltz" => parse_branch_zero(&args)
+            .map(|(rs, offset)| vec![Instruction::Blt(B::new(rs, Register::ZERO, offset))]),
+        "bgtz" => parse_branch_zero(&args).map(|(rs, offset)| {
+            vec![Instruction::Blt(B::new(
+                Register::ZERO,
+                rs.to_role(),
+                offset,
+            ))]
+        }),
+        "bgt" => parse_swapped_branch(&args).map(|b| vec![Instruction::Blt(b)]),
+        "ble" => parse_swapped_branch(&args).map(|b| vec![Instruction::Bge(b)]),
+        "bgtu" => parse_swapped_branch(&args).map(|b| vec![Instruction::Bltu(b)]),
+        "bleu" => parse_swapped_branch(&args).map(|b| vec![Instruction::Bgeu(b)]),
+        "j" => parse_jump_offset(&args)
+            .map(|offset| vec![Instruction::Jal(J::new(Register::ZERO, offset))]),
+        "jal" if args.len() == 1 => {
+            parse_jump_offset(&args).map(|offset| vec![Instruction::Jal(J::new(ra(), offset))])
+        }
+        "jr" => parse_rs(&args)
+            .map(|rs| vec![Instruction::Jalr(I::new(Register::ZERO, rs, zero_offset()))]),
+        "jalr" if args.len() == 1 => {
+            parse_rs(&args).map(|rs| vec![Instruction::Jalr(I::new(ra(), rs, zero_offset()))])
+        }
+        "ret" => parse_help(
+            &args,
+            (
+                Some(|| {
+                    Ok(vec![Instruction::Jalr(I::new(
+                        Register::ZERO,
+                        ra(),
+                        zero_offset(),
+                    ))])
+                }),
+                None,
+                None,
+                None,
+            ),
+        ),
+        "call" => parse_far_offset(&args).map(|offset| far_jump(ra(), ra(), offset.to_i32())),
+        "tail" => {
+            parse_far_offset(&args).map(|offset| far_jump(Register::ZERO, t1(), offset.to_i32()))
+        }
+        "csrr" => parse_help(
+            &args,
+            (
+                None,
+                None,
+                Some(|rd, csr| {
+                    Ok(vec![Instruction::Csrrs(Csr::new(
+                        parse_register(0, rd)?,
+                        Register::ZERO,
+                        parse_immediate(1, csr)?,
+                    ))])
+                }),
+                None,
+            ),
+        ),
+        "csrw" => parse_csr_write(&args).map(|csr| vec![Instruction::Csrrw(csr)]),
+        "csrs" => parse_csr_write(&args).map(|csr| vec![Instruction::Csrrs(csr)]),
+        "csrc" => parse_csr_write(&args).map(|csr| vec![Instruction::Csrrc(csr)]),
+        "csrwi" => parse_csr_write_immediate(&args).map(|csri| vec![Instruction::Csrrwi(csri)]),
+        "csrsi" => parse_csr_write_immediate(&args).map(|csri| vec![Instruction::Csrrsi(csri)]),
+        "csrci" => parse_csr_write_immediate(&args).map(|csri| vec![Instruction::Csrrci(csri)]),
+        "rdcycle" => parse_rd(&args).map(|rd| csr_read(rd, 0xC00)),
+        "rdtime" => parse_rd(&args).map(|rd| csr_read(rd, 0xC01)),
+        "rdinstret" => parse_rd(&args).map(|rd| csr_read(rd, 0xC02)),
+        "rdcycleh" => parse_rd(&args).map(|rd| csr_read(rd, 0xC80)),
+        "rdtimeh" => parse_rd(&args).map(|rd| csr_read(rd, 0xC81)),
+        "rdinstreth" => parse_rd(&args).map(|rd| csr_read(rd, 0xC82)),
+        _ => return None,
+    };
+
+    Some(sequence)
+}
+
+/* The names of every pseudo-instruction that `expand` accepts.
+ */
+pub const MNEMONICS: &[&str] = &[
+    "nop",
+    "li",
+    "mv",
+    "not",
+    "neg",
+    "seqz",
+    "snez",
+    "sltz",
+    "sgtz",
+    "beqz",
+    "bnez",
+    "blez",
+    "bgez",
+    "bltz",
+    "bgtz",
+    "bgt",
+    "ble",
+    "bgtu",
+    "bleu",
+    "j",
+    "jal",
+    "jr",
+    "jalr",
+    "ret",
+    "call",
+    "tail",
+    "csrr",
+    "csrw",
+    "csrs",
+    "csrc",
+    "csrwi",
+    "csrsi",
+    "csrci",
+    "rdcycle",
+    "rdtime",
+    "rdinstret",
+    "rdcycleh",
+    "rdtimeh",
+    "rdinstreth",
+];
+
+/* Parses either a pseudo-instruction or a single instruction. Unknown names
+ * are compared against both sets of mnemonics for suggestions.
+ */
+pub fn parse(mnemonic: &str) -> Sequence {
+    expand(mnemonic).unwrap_or_else(|| match mnemonic.parse() {
+        Ok(instruction) => Ok(vec![instruction]),
+        Err(Error::InvalidInstructionName { name, .. }) => {
+            Err(instruction::invalid_instruction_name(
+                &name,
+                instruction::mnemonics().chain(MNEMONICS.iter().cloned()),
+            ))
+        }
+        Err(e) => Err(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(mnemonic: &str) -> Vec<u32> {
+        parse(mnemonic)
+            .unwrap_or_else(|e| panic!("{}: {:?}", mnemonic, e))
+            .iter()
+            .map(Instruction::to_u32)
+            .collect()
+    }
+
+    #[test]
+    fn expand_pseudo_instructions() {
+        let expected: &[(&str, &[&str])] = &[
+            ("nop", &["addi zero, zero, 0"]),
+            ("li t0, -2048", &["addi t0, zero, -2048"]),
+            (
+                "li t0, 0xFF010203",
+                &["lui t0, 0xFF010", "addi t0, t0, 515"],
+            ),
+            ("li a0, 0x800", &["lui a0, 1", "addi a0, a0, -2048"]),
+            ("li a0, 0x12345000", &["lui a0, 0x12345"]),
+            ("li a0, 0xFFFFFFFF", &["addi a0, zero, -1"]),
+            ("li a0, -2147483648", &["lui a0, 0x80000"]),
+            ("mv a0, a1", &["addi a0, a1, 0"]),
+            ("not a0, a1", &["xori a0, a1, -1"]),
+            ("neg a0, a1", &["sub a0, zero, a1"]),
+            ("seqz a0, a1", &["sltiu a0, a1, 1"]),
+            ("snez a0, a1", &["sltu a0, zero, a1"]),
+            ("sltz a0, a1", &["slt a0, a1, zero"]),
+            ("sgtz a0, a1", &["slt a0, zero, a1"]),
+            ("beqz a0, 8", &["beq a0, zero, 8"]),
+            ("bnez a0, 8", &["bne a0, zero, 8"]),
+            ("blez a0, 8", &["bge zero, a0, 8"]),
+            ("bgez a0, 8", &["bge a0, zero, 8"]),
+            ("bltz a0, 8", &["blt a0, zero, 8"]),
+            ("bgtz a0, 8", &["blt zero, a0, 8"]),
+            ("bgt a0, a1, 8", &["blt a1, a0, 8"]),
+            ("ble a0, a1, 8", &["bge a1, a0, 8"]),
+            ("bgtu a0, a1, 8", &["bltu a1, a0, 8"]),
+            ("bleu a0, a1, 8", &["bgeu a1, a0, 8"]),
+            ("j -4", &["jal zero, -4"]),
+            ("jal -4", &["jal ra, -4"]),
+            ("jr a0", &["jalr zero, a0, 0"]),
+            ("jalr a0", &["jalr ra, a0, 0"]),
+            ("ret", &["jalr zero, ra, 0"]),
+            ("call 0x1800", &["auipc ra, 2", "jalr ra, ra, -2048"]),
+            ("tail -8", &["auipc t1, 0", "jalr zero, t1, -8"]),
+            ("csrr a0, cycle", &["csrrs a0, cycle, zero"]),
+            ("csrw 0x340, a0", &["csrrw zero, 0x340, a0"]),
+            ("csrs 0x340, a0", &["csrrs zero, 0x340, a0"]),
+            ("csrc 0x340, a0", &["csrrc zero, 0x340, a0"]),
+            ("csrwi 0x340, 1", &["csrrwi zero, 0x340, 1"]),
+            ("csrsi 0x340, 1", &["csrrsi zero, 0x340, 1"]),
+            ("csrci 0x340, 1", &["csrrci zero, 0x340, 1"]),
+            ("rdcycle a0", &["csrrs a0, cycle, zero"]),
+            ("rdtime a0", &["csrrs a0, time, zero"]),
+            ("rdinstret a0", &["csrrs a0, instret, zero"]),
+            ("rdcycleh a0", &["csrrs a0, cycleh, zero"]),
+            ("rdtimeh a0", &["csrrs a0, timeh, zero"]),
+            ("rdinstreth a0", &["csrrs a0, instreth, zero"]),
+        ];
+
+        for (pseudo, expansion) in expected {
+            let expansion: Vec<u32> = expansion.iter().flat_map(|m| encode(m)).collect();
+            assert_eq!(encode(pseudo), expansion, "{}", pseudo);
+        }
+    }
+
+    #[test]
+    fn parse_falls_back_to_instructions() {
+        assert!(expand("add a0, a1, a2").is_none());
+        assert!(expand("jal ra, 8").is_none());
+        assert_eq!(encode("jal ra, 8"), encode("jal 8"));
+        assert!(parse("li a0").is_err());
+        assert!(parse("bogus a0").is_err());
+        match parse("rett") {
+            Err(Error::InvalidInstructionName {
+                suggestion: Some("ret"),
+                ..
+            }) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    /* The prompt parses each line as it is typed, so input that is not ASCII
+     * must give an error rather than a panic.
+     */
+    #[test]
+    fn non_ascii_operands() {
+        for line in &[
+            "jalr é",
+            "addi a0, é, 1",
+            "addi a0, a0, é",
+            "addi a0, a0, 0é",
+            "li é, 1",
+        ] {
+            assert!(parse(line).is_err(), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::immediate::{self, Constraints, Immediate};
+use crate::instruction::{Csr, Csri, Instruction, Load, Shift, B, I, J, R, S, U};
+use crate::register::{self, Register};
+
+/* A small xorshift64* generator, so that the same seed always gives the
+ * same instructions.
+ */
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        /* xorshift gets stuck at zero, so the seed is scrambled first. */
+        let state = (seed ^ 0x9E37_79B9_7F4A_7C15).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        Rng(if state == 0 { 1 } else { state })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /* A number in `0..n`. */
+    pub fn below(&mut self, n: u32) -> u32 {
+        (((self.next_u64() >> 32) * u64::from(n)) >> 32) as u32
+    }
+
+    /* A number in `min..=max`. */
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        let count = (i64::from(max) - i64::from(min) + 1) as u64;
+        (i64::from(min) + (self.next_u64() % count) as i64) as i32
+    }
+}
+
+pub fn register<R>(rng: &mut Rng) -> Register<R> {
+    Register::from_u32(rng.below(register::GPR_COUNT)).unwrap()
+}
+
+/* An immediate that satisfies all of the constraints of `X`.
+ */
+pub fn immediate<X: Constraints>(rng: &mut Rng) -> Immediate<X> {
+    loop {
+        let mut value = rng.range(X::MIN, X::MAX);
+        if X::EVEN {
+            value &= !1;
+        }
+        value -= value % X::MULTIPLE_OF;
+        if let Ok(imm) = Immediate::from_i32(value) {
+            return imm;
+        }
+    }
+}
+
+/* One of the CSRs narvie keeps. Its CSR file (CSR_iCE40UP5K.v in the sail
+ * submodule) is a plain register at each of the addresses 0x000 to 0x3FF,
+ * with no counters and nothing read only.
+ */
+fn csr(rng: &mut Rng) -> Immediate<immediate::CsrSpecifier> {
+    Immediate::from_i32(rng.range(0, 0x3FF)).unwrap()
+}
+
+fn i(rng: &mut Rng) -> I {
+    I::new(register(rng), register(rng), immediate(rng))
+}
+
+fn r(rng: &mut Rng) -> R {
+    R::new(register(rng), register(rng), register(rng))
+}
+
+fn b(rng: &mut Rng) -> B {
+    B::new(register(rng), register(rng), immediate(rng))
+}
+
+/* An offset from `zero` to one of the words at 0, 4, ... 2044, which are
+ * inside narvie's 2048 words of data memory. narvie keeps a whole word at
+ * every address, so only aligned `lw` and `sw` there behave as they would on
+ * any other RISC-V processor.
+ */
+fn word_offset<X: Constraints>(rng: &mut Rng) -> Immediate<X> {
+    Immediate::from_i32(rng.range(0, 511) * 4).unwrap()
+}
+
+fn lw(rng: &mut Rng) -> Load {
+    Load::new(register(rng), Register::ZERO, word_offset(rng))
+}
+
+fn sw(rng: &mut Rng) -> S {
+    S::new(Register::ZERO, register(rng), word_offset(rng))
+}
+
+fn shift(rng: &mut Rng) -> Shift {
+    Shift::new(register(rng), register(rng), immediate(rng))
+}
+
+fn csr_register(rng: &mut Rng) -> Csr {
+    Csr::new(register(rng), register(rng), csr(rng))
+}
+
+fn csr_immediate(rng: &mut Rng) -> Csri {
+    Csri::new(register(rng), immediate(rng), csr(rng))
+}
+
+/* Instructions without operands to choose are made from their encoding. */
+fn word(word: u32) -> Instruction {
+    Instruction::from_u32(word).unwrap()
+}
+
+/* A random RV32I or Zicsr instruction, each equally likely. Memory is only
+ * accessed with `lw` and `sw` at a word inside narvie's data memory.
+ */
+pub fn instruction(rng: &mut Rng) -> Instruction {
+    match rng.below(41) {
+        0 => Instruction::Lui(U::new(register(rng), immediate(rng))),
+        1 => Instruction::Auipc(U::new(register(rng), immediate(rng))),
+        2 => Instruction::Jal(J::new(register(rng), immediate(rng))),
+        3 => Instruction::Jalr(i(rng)),
+        4 => Instruction::Beq(b(rng)),
+        5 => Instruction::Bne(b(rng)),
+        6 => Instruction::Blt(b(rng)),
+        7 => Instruction::Bge(b(rng)),
+        8 => Instruction::Bltu(b(rng)),
+        9 => Instruction::Bgeu(b(rng)),
+        10 => Instruction::Lw(lw(rng)),
+        11 => Instruction::Sw(sw(rng)),
+        12 => Instruction::Addi(i(rng)),
+        13 => Instruction::Slti(i(rng)),
+        14 => Instruction::Sltiu(i(rng)),
+        15 => Instruction::Xori(i(rng)),
+        16 => Instruction::Ori(i(rng)),
+        17 => Instruction::Andi(i(rng)),
+        18 => Instruction::Slli(shift(rng)),
+        19 => Instruction::Srli(shift(rng)),
+        20 => Instruction::Srai(shift(rng)),
+        21 => Instruction::Add(r(rng)),
+        22 => Instruction::Sub(r(rng)),
+        23 => Instruction::Sll(r(rng)),
+        24 => Instruction::Slt(r(rng)),
+        25 => Instruction::Sltu(r(rng)),
+        26 => Instruction::Xor(r(rng)),
+        27 => Instruction::Srl(r(rng)),
+        28 => Instruction::Sra(r(rng)),
+        29 => Instruction::Or(r(rng)),
+        30 => Instruction::And(r(rng)),
+        31 => {
+            let pred = rng.range(1, 15) as u32;
+            let succ = rng.range(1, 15) as u32;
+            word((pred << 24) | (succ << 20) | 0b000_1111)
+        }
+        32 => word(0x0000_100F),
+        33 => word(0x0000_0073),
+        34 => word(0x0010_0073),
+        35 => Instruction::Csrrw(csr_register(rng)),
+        36 => Instruction::Csrrs(csr_register(rng)),
+        37 => Instruction::Csrrc(csr_register(rng)),
+        38 => Instruction::Csrrwi(csr_immediate(rng)),
+        39 => Instruction::Csrrsi(csr_immediate(rng)),
+        _ => Instruction::Csrrci(csr_immediate(rng)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn same_seed_same_instructions() {
+        let words = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..100)
+                .map(|_| instruction(&mut rng).to_u32())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(words(7), words(7));
+        assert_ne!(words(7), words(8));
+    }
+
+    #[test]
+    fn instructions_are_valid() {
+        let mut rng = Rng::new(0);
+        for _ in 0..10_000 {
+            let instruction = instruction(&mut rng);
+            let word = instruction.to_u32();
+            let decoded = Instruction::from_u32(word).unwrap();
+            assert_eq!(decoded.to_u32(), word, "{}", instruction);
+
+            let parsed = Instruction::from_str(&instruction.to_string()).unwrap();
+            assert_eq!(parsed.to_u32(), word, "{}", instruction);
+        }
+    }
+
+    #[test]
+    fn words_inside_data_memory() {
+        let mut rng = Rng::new(0);
+        for _ in 0..10_000 {
+            let word = instruction(&mut rng).to_u32();
+            let offset = match word & 0x7f {
+                0b000_0011 => word >> 20,
+                0b010_0011 => ((word >> 20) & !0x1f) | ((word >> 7) & 0x1f),
+                _ => continue,
+            };
+            assert_eq!((word >> 12) & 0x7, 0b010, "{:08x}", word);
+            assert_eq!((word >> 15) & 0x1f, 0, "{:08x}", word);
+            assert!(offset < 2048 && offset % 4 == 0, "{:08x}", word);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use crate::register::{GetRegisterError, Register, GPR_COUNT};
+use prettytable::{format, Cell, Row, Table};
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+/* The values of the 32 general purpose registers, as sent back by narvie
+ * after each instruction.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RegisterFile([u32; 32]);
+
+/* A register whose value differs between two register files.
+ */
+#[derive(Debug)]
+pub struct Change {
+    pub register: Register<()>,
+    pub old: u32,
+    pub new: u32,
+}
+
+impl RegisterFile {
+    /* The size of the register file sent by narvie over the UART: each
+     * register as 4 little endian bytes, starting with `x0`.
+     */
+    pub const BYTES: usize = 4 * GPR_COUNT as usize;
+
+    pub fn new(values: [u32; 32]) -> Self {
+        RegisterFile(values)
+    }
+
+    pub fn from_le_bytes(bytes: &[u8; RegisterFile::BYTES]) -> Self {
+        let mut values = [0; 32];
+        for (value, chunk) in values.iter_mut().zip(bytes.chunks(4)) {
+            *value = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        RegisterFile(values)
+    }
+
+    pub fn to_le_bytes(&self) -> [u8; RegisterFile::BYTES] {
+        let mut bytes = [0; RegisterFile::BYTES];
+        for (chunk, value) in bytes.chunks_mut(4).zip(self.0.iter()) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    /* The value of a register given as `x0` to `x31` or by its ABI name.
+     */
+    pub fn get(&self, name: &str) -> Result<u32, GetRegisterError> {
+        Register::<()>::from_str(name).map(|register| self[register])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Register<()>, u32)> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (Register::from_u32(i as u32).unwrap(), *value))
+    }
+
+    /* The registers whose value in `other` is different, treating `self` as
+     * the older of the two.
+     */
+    pub fn diff(&self, other: &RegisterFile) -> Vec<Change> {
+        self.iter()
+            .zip(other.iter())
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((register, old), (_, new))| Change { register, old, new })
+            .collect()
+    }
+}
+
+impl From<[u32; 32]> for RegisterFile {
+    fn from(values: [u32; 32]) -> Self {
+        RegisterFile(values)
+    }
+}
+
+impl<R> Index<Register<R>> for RegisterFile {
+    type Output = u32;
+
+    fn index(&self, register: Register<R>) -> &u32 {
+        &self.0[register.to_u32() as usize]
+    }
+}
+
+impl<R> IndexMut<Register<R>> for RegisterFile {
+    fn index_mut(&mut self, register: Register<R>) -> &mut u32 {
+        &mut self.0[register.to_u32() as usize]
+    }
+}
+
+impl<R> Index<&Register<R>> for RegisterFile {
+    type Output = u32;
+
+    fn index(&self, register: &Register<R>) -> &u32 {
+        &self.0[register.to_u32() as usize]
+    }
+}
+
+impl<R> IndexMut<&Register<R>> for RegisterFile {
+    fn index_mut(&mut self, register: &Register<R>) -> &mut u32 {
+        &mut self.0[register.to_u32() as usize]
+    }
+}
+
+/* Panics if `name` is not a register, use `get` to handle that.
+ */
+impl Index<&str> for RegisterFile {
+    type Output = u32;
+
+    fn index(&self, name: &str) -> &u32 {
+        match Register::<()>::from_str(name) {
+            Ok(register) => &self[register],
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+/* Ways of displaying the value held in a register, several can be shown side
+ * by side, one per column.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumericView {
+    Hexadecimal,
+    Signed,
+    Unsigned,
+    Binary,
+    Ascii,
+}
+
+pub const NUMERIC_VIEWS: &[(&str, NumericView)] = &[
+    ("hex", NumericView::Hexadecimal),
+    ("signed", NumericView::Signed),
+    ("unsigned", NumericView::Unsigned),
+    ("binary", NumericView::Binary),
+    ("ascii", NumericView::Ascii),
+];
+
+impl NumericView {
+    pub fn header(&self) -> &'static str {
+        match self {
+            NumericView::Hexadecimal => "Value",
+            NumericView::Signed => "Signed",
+            NumericView::Unsigned => "Unsigned",
+            NumericView::Binary => "Binary",
+            NumericView::Ascii => "ASCII",
+        }
+    }
+
+    pub fn format(&self, value: u32) -> String {
+        match self {
+            NumericView::Hexadecimal => format!("0x{:08X}", value),
+            NumericView::Signed => format!("{}", value as i32),
+            NumericView::Unsigned => format!("{}", value),
+            NumericView::Binary => value
+                .to_be_bytes()
+                .iter()
+                .map(|byte| format!("{:08b}", byte))
+                .collect::<Vec<_>>()
+                .join(" "),
+            /* Most significant byte first, matching the hexadecimal view. */
+            NumericView::Ascii => value
+                .to_be_bytes()
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+fn register_header(views: &[NumericView]) -> Row {
+    Row::new(
+        ["Name", "ABI"]
+            .iter()
+            .cloned()
+            .chain(views.iter().map(NumericView::header))
+            .map(Cell::new)
+            .collect(),
+    )
+}
+
+fn register_row(
+    register: &Register<()>,
+    value: u32,
+    previous: Option<u32>,
+    views: &[NumericView],
+) -> Row {
+    let mut row = Row::new(vec![
+        Cell::new(&register.to_string()),
+        Cell::new(&register.abi_name()),
+    ]);
+    for view in views {
+        row.add_cell(match previous {
+            Some(old) if old != value => {
+                Cell::new(&format!("{} → {}", view.format(old), view.format(value)))
+                    .style_spec("bFg")
+            }
+            _ => Cell::new(&view.format(value)),
+        });
+    }
+    row
+}
+
+impl RegisterFile {
+    /* `x0` to `x15` on the left and `x16` to `x31` on the right. Registers
+     * whose value differs from `previous` are highlighted and shown with both
+     * their old and new values.
+     */
+    pub fn table(&self, previous: Option<&RegisterFile>, views: &[NumericView]) -> Table {
+        const COLUMNS: u32 = 2;
+        const ROWS: u32 = GPR_COUNT / 2;
+        if ROWS * COLUMNS != GPR_COUNT {
+            panic!("Expected an even number of registers");
+        }
+        let mut table = Table::init(vec![Row::new(
+            (0..COLUMNS)
+                .map(|i| {
+                    let mut side_table = Table::init(vec![register_header(views)]);
+                    side_table.extend((0..ROWS).map(|j| {
+                        let register = Register::from_u32(i * ROWS + j).unwrap();
+                        register_row(
+                            &register,
+                            self[&register],
+                            previous.map(|p| p[&register]),
+                            views,
+                        )
+                    }));
+
+                    side_table.set_format(*format::consts::FORMAT_BOX_CHARS);
+                    prettytable::cell!(side_table)
+                })
+                .collect::<Vec<Cell>>(),
+        )]);
+        table.set_format(*format::consts::FORMAT_CLEAN);
+        table
+    }
+
+    /* Only the registers whose value differs from `previous`.
+     */
+    pub fn changes_table(&self, previous: &RegisterFile, views: &[NumericView]) -> Table {
+        let mut table = Table::init(vec![register_header(views)]);
+        table.extend(
+            previous
+                .diff(self)
+                .iter()
+                .map(|change| register_row(&change.register, change.new, Some(change.old), views)),
+        );
+        table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        table
+    }
+}
+
+/* The register table printed by `narvie-cli`, with values in hexadecimal.
+ */
+impl fmt::Display for RegisterFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.table(None, &[NumericView::Hexadecimal]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_and_names() {
+        let bytes: Vec<u8> = (0..32u32)
+            .flat_map(|i| (i * 3).to_le_bytes().to_vec())
+            .collect();
+        let mut payload = [0; RegisterFile::BYTES];
+        payload.copy_from_slice(&bytes);
+
+        let reg_file = RegisterFile::from_le_bytes(&payload);
+        assert_eq!(reg_file["a0"], 30);
+        assert_eq!(reg_file["x10"], 30);
+        assert_eq!(reg_file[Register::<()>::from_u32(31).unwrap()], 93);
+        assert!(reg_file.get("a8").is_err());
+        assert_eq!(reg_file.to_le_bytes().to_vec(), bytes);
+    }
+
+    #[test]
+    fn diff() {
+        let old = RegisterFile::default();
+        let mut new = old;
+        new[Register::<()>::from_u32(5).unwrap()] = 7;
+
+        let changes = old.diff(&new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].register.abi_name(), "t0");
+        assert_eq!((changes[0].old, changes[0].new), (0, 7));
+        assert!(new.diff(&new).is_empty());
+    }
+}
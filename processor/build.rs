@@ -3,6 +3,10 @@ use std::io::{self, Write};
 use std::process::Command;
 
 fn main() {
+    if env::var_os("CARGO_FEATURE_VERILATOR").is_none() {
+        return;
+    }
+
     let out_dir = env::var("OUT_DIR").unwrap();
 
     env::set_current_dir("build").unwrap();
@@ -1,26 +1,46 @@
-use std::io::{Read, Write};
-use std::net::TcpListener;
-use std::sync::mpsc;
-use std::thread;
+extern crate clap;
 
-mod lib;
+use clap::{App, Arg};
+use narvie_processor::model;
+use std::env;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::process::{self, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
 
 enum Message {
     Uart(u8),
     Done,
 }
 
-fn main() {
+#[cfg(feature = "verilator")]
+fn simulate(use_model: bool, sender: Sender<u8>, receiver: Receiver<u8>) {
+    if use_model {
+        model::run_model(sender, receiver);
+    } else {
+        narvie_processor::run_narvie(sender, receiver);
+    }
+}
+
+#[cfg(not(feature = "verilator"))]
+fn simulate(use_model: bool, sender: Sender<u8>, receiver: Receiver<u8>) {
+    if !use_model {
+        eprintln!("narvie-processor was built without Verilator, running the software model");
+    }
+    model::run_model(sender, receiver);
+}
+
+/* Clients take turns with a single simulation, which keeps its state between
+ * connections.
+ */
+fn serve_shared(use_model: bool, listener: TcpListener) {
     let (sender, receiver) = mpsc::channel();
     let (sender2, receiver2) = mpsc::channel();
     let master_channel = mpsc::channel();
     let master_sender1 = master_channel.0.clone();
 
-    let listener = TcpListener::bind(("localhost", 8001)).unwrap();
-
-    thread::spawn(move || {
-        lib::run_narvie(sender2, receiver);
-    });
+    thread::spawn(move || simulate(use_model, sender2, receiver));
 
     thread::spawn(move || {
         while let Ok(data) = receiver2.recv() {
@@ -60,3 +80,173 @@ fn main() {
         } {}
     }
 }
+
+/* Runs a simulation of its own for the client at the other end of `reader`
+ * and `writer`, which is stopped once the client stops sending.
+ */
+fn serve<R: Read + Send + 'static, W: Write>(
+    use_model: bool,
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<()> {
+    let (to_simulation, from_client) = mpsc::channel();
+    let (to_client, from_simulation) = mpsc::channel();
+    thread::spawn(move || simulate(use_model, to_client, from_client));
+
+    thread::spawn(move || {
+        let mut buf = [0];
+        while reader
+            .read(&mut buf)
+            .map(|bytes_read| bytes_read == 1)
+            .unwrap_or(false)
+        {
+            if to_simulation.send(buf[0]).is_err() {
+                break;
+            }
+        }
+    });
+
+    for byte in from_simulation {
+        writer.write_all(&[byte])?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/* Passes bytes on as soon as they are read. `io::copy` is not used, as on
+ * Linux it splices from a socket into a pipe, which can hold back an
+ * instruction until more bytes arrive.
+ */
+fn forward<R: Read, W: Write>(mut reader: R, mut writer: W) -> io::Result<()> {
+    let mut buf = [0; 128];
+    loop {
+        match reader.read(&mut buf)? {
+            0 => return Ok(()),
+            bytes_read => writer.write_all(&buf[..bytes_read])?,
+        }
+    }
+}
+
+/* Verilator builds narvie without `--threads`, so Verilator simulations are
+ * not known to be safe to run side by side in one process. Each one runs in
+ * a child narvie-processor instead, which talks to the client over its stdin
+ * and stdout.
+ */
+fn serve_in_child<R: Read + Send + 'static, W: Write>(reader: R, writer: W) -> io::Result<()> {
+    let mut child = Command::new(env::current_exe()?)
+        .arg("--stdio")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let to_child = child.stdin.take().unwrap();
+    thread::spawn(move || forward(reader, to_child));
+
+    let result = forward(child.stdout.take().unwrap(), writer);
+    if result.is_err() {
+        let _ = child.kill();
+    }
+    child.wait()?;
+    result
+}
+
+/* A client with a simulation of its own, started with a fresh register file
+ * when it connects and stopped when it disconnects.
+ */
+fn serve_client(use_model: bool, stream: TcpStream) -> io::Result<()> {
+    let result = if use_model || cfg!(not(feature = "verilator")) {
+        serve(true, stream.try_clone()?, &stream)
+    } else {
+        serve_in_child(stream.try_clone()?, &stream)
+    };
+    /* Stops the reader too, if it is still waiting for the client. */
+    let _ = stream.shutdown(Shutdown::Both);
+    result
+}
+
+fn serve_each_client(use_model: bool, listener: TcpListener) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    if let Err(e) = serve_client(use_model, stream) {
+                        eprintln!("Lost connection to a client: {}", e);
+                    }
+                });
+            }
+            Err(e) => eprintln!("Cannot accept a connection: {}", e),
+        }
+    }
+}
+
+fn main() {
+    let matches = App::new("narvie-processor")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author("Harry Sarson <harry.sarson@hotmail.co.uk>")
+        .about("Serve a simulation of the narvie processor over tcp")
+        .arg(
+            Arg::with_name("bind")
+                .long("bind")
+                .value_name("ADDR:PORT")
+                .takes_value(true)
+                .help("The address to listen on, localhost:8001 by default."),
+        )
+        .arg(
+            Arg::with_name("model")
+                .long("model")
+                .help("Run the software model instead of the Verilator simulation."),
+        )
+        .arg(Arg::with_name("multi-client").long("multi-client").help(
+            "Give every connection its own simulation, so that many clients can use \
+                     the server at once.",
+        ))
+        .arg(
+            /* Used by `--multi-client` to run each Verilator simulation in a
+             * process of its own.
+             */
+            Arg::with_name("stdio").long("stdio").hidden(true),
+        )
+        .get_matches();
+
+    let use_model = matches.is_present("model");
+
+    if matches.is_present("stdio") {
+        if let Err(e) = serve(use_model, io::stdin(), io::stdout()) {
+            eprintln!("Lost connection to narvie-processor: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let address = matches.value_of("bind").unwrap_or("localhost:8001");
+    let listener = TcpListener::bind(address).unwrap_or_else(|e| {
+        eprintln!("narvie-processor cannot listen on {}: {}", address, e);
+        process::exit(1);
+    });
+
+    if matches.is_present("multi-client") {
+        serve_each_client(use_model, listener);
+    } else {
+        serve_shared(use_model, listener);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use narvie_isa::device::{self, NarvieDevice};
+
+    #[test]
+    fn clients_have_their_own_model() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve_each_client(true, listener));
+
+        let mut first = device::tcp(address).unwrap();
+        let mut second = device::tcp(address).unwrap();
+        assert_eq!(first.execute(0x0050_0513).unwrap()["a0"], 5); // addi a0, zero, 5
+        assert_eq!(second.execute(0x0000_0013).unwrap()["a0"], 0); // nop
+        assert_eq!(second.execute(0x0070_0513).unwrap()["a0"], 7); // addi a0, zero, 7
+        assert_eq!(first.execute(0x0000_0013).unwrap()["a0"], 5); // nop
+    }
+}
@@ -0,0 +1,388 @@
+use narvie_isa::device::{DeviceError, NarvieDevice};
+use narvie_isa::register_file::RegisterFile;
+use std::sync::mpsc::{Receiver, Sender};
+
+/* A software model of the narvie processor, implementing RV32I and Zicsr.
+ *
+ * Unlike the Verilator simulation this needs no C++ toolchain. Instructions
+ * are evaluated one at a time, as they are by narvie. The program counter
+ * starts at zero and moves on by 4 after every instruction unless a jump or
+ * taken branch changes it, so `auipc` and the link address of jumps depend on
+ * how many instructions have been evaluated. There are no traps: illegal
+ * instructions, `ecall` and `ebreak` do nothing and writes to read only CSRs
+ * are ignored.
+ *
+ * Data memory copies narvie's (`datamem` in data_memory_iCE40UP5K.v): a 32
+ * bit word at each of the addresses 0 to 2047. Every load reads the whole word
+ * at its address and every store writes the whole of rs2 there, whatever its
+ * width, so `lb` and `lh` are not sign extended and `sb` and `sh` write all 32
+ * bits. Stores to addresses outside of this are ignored and loads from them
+ * read zero.
+ */
+pub struct Model {
+    registers: [u32; 32],
+    pc: u32,
+    memory: Vec<u32>,
+    csrs: Vec<u32>,
+    instret: u64,
+}
+
+/* Words of data memory, one at each address, as on the narvie processor.
+ */
+pub const MEMORY_WORDS: usize = 2048;
+
+const CSR_COUNT: usize = 4096;
+
+mod opcode {
+    pub const LUI: u32 = 0b011_0111;
+    pub const AUIPC: u32 = 0b001_0111;
+    pub const JAL: u32 = 0b110_1111;
+    pub const JALR: u32 = 0b110_0111;
+    pub const BRANCH: u32 = 0b110_0011;
+    pub const LOAD: u32 = 0b000_0011;
+    pub const STORE: u32 = 0b010_0011;
+    pub const OP_IMM: u32 = 0b001_0011;
+    pub const OP: u32 = 0b011_0011;
+    pub const MISC_MEM: u32 = 0b000_1111;
+    pub const SYSTEM: u32 = 0b111_0011;
+}
+
+fn sign_extend(value: u32, bits: u32) -> u32 {
+    let shift = 32 - bits;
+    ((value << shift) as i32 >> shift) as u32
+}
+
+fn alu(funct3: u32, alternate: bool, a: u32, b: u32) -> u32 {
+    match funct3 {
+        0b000 if alternate => a.wrapping_sub(b),
+        0b000 => a.wrapping_add(b),
+        0b001 => a << (b & 0x1f),
+        0b010 => ((a as i32) < (b as i32)) as u32,
+        0b011 => (a < b) as u32,
+        0b100 => a ^ b,
+        0b101 if alternate => ((a as i32) >> (b & 0x1f)) as u32,
+        0b101 => a >> (b & 0x1f),
+        0b110 => a | b,
+        _ => a & b,
+    }
+}
+
+impl Model {
+    pub fn new() -> Self {
+        Model {
+            registers: [0; 32],
+            pc: 0,
+            memory: vec![0; MEMORY_WORDS],
+            csrs: vec![0; CSR_COUNT],
+            instret: 0,
+        }
+    }
+
+    pub fn registers(&self) -> [u32; 32] {
+        self.registers
+    }
+
+    fn load(&self, address: u32) -> u32 {
+        self.memory.get(address as usize).copied().unwrap_or(0)
+    }
+
+    fn store(&mut self, address: u32, value: u32) {
+        if let Some(word) = self.memory.get_mut(address as usize) {
+            *word = value;
+        }
+    }
+
+    /* `cycle`, `time` and `instret` all count the instructions evaluated.
+     */
+    fn read_csr(&self, csr: u32) -> u32 {
+        match csr {
+            0xC00..=0xC02 => self.instret as u32,
+            0xC80..=0xC82 => (self.instret >> 32) as u32,
+            _ => self.csrs[csr as usize],
+        }
+    }
+
+    fn write_csr(&mut self, csr: u32, value: u32) {
+        /* The top two bits of the address are set for read only CSRs. */
+        if csr >> 10 != 0b11 {
+            self.csrs[csr as usize] = value;
+        }
+    }
+
+    /* Reads the CSR, updates it and returns the old value.
+     */
+    fn csr_instruction(&mut self, instruction: u32, source: u32) -> Option<u32> {
+        let funct3 = (instruction >> 12) & 0x7;
+        let rs1 = (instruction >> 15) & 0x1f;
+        let csr = instruction >> 20;
+
+        let old = self.read_csr(csr);
+        let new = match funct3 & 0b11 {
+            0b01 => source,
+            0b10 => old | source,
+            0b11 => old & !source,
+            _ => return None,
+        };
+        /* csrrs and csrrc do not write when the source is x0 or zero. */
+        if funct3 & 0b11 == 0b01 || rs1 != 0 {
+            self.write_csr(csr, new);
+        }
+        Some(old)
+    }
+
+    pub fn execute(&mut self, instruction: u32) {
+        let opcode = instruction & 0x7f;
+        let rd = ((instruction >> 7) & 0x1f) as usize;
+        let funct3 = (instruction >> 12) & 0x7;
+        let rs1 = (instruction >> 15) & 0x1f;
+        let rs2 = (instruction >> 20) & 0x1f;
+        let funct7 = instruction >> 25;
+
+        let a = self.registers[rs1 as usize];
+        let b = self.registers[rs2 as usize];
+
+        let imm_i = sign_extend(instruction >> 20, 12);
+        let imm_s = sign_extend(
+            ((instruction >> 20) & !0x1f) | ((instruction >> 7) & 0x1f),
+            12,
+        );
+        let imm_b = sign_extend(
+            ((instruction >> 19) & 0x1000)
+                | ((instruction << 4) & 0x800)
+                | ((instruction >> 20) & 0x7e0)
+                | ((instruction >> 7) & 0x1e),
+            13,
+        );
+        let imm_u = instruction & 0xffff_f000;
+        let imm_j = sign_extend(
+            ((instruction >> 11) & 0x10_0000)
+                | (instruction & 0xf_f000)
+                | ((instruction >> 9) & 0x800)
+                | ((instruction >> 20) & 0x7fe),
+            21,
+        );
+
+        let link = self.pc.wrapping_add(4);
+        let mut next_pc = link;
+
+        let result = match opcode {
+            opcode::LUI => Some(imm_u),
+            opcode::AUIPC => Some(self.pc.wrapping_add(imm_u)),
+            opcode::JAL => {
+                next_pc = self.pc.wrapping_add(imm_j);
+                Some(link)
+            }
+            opcode::JALR if funct3 == 0 => {
+                next_pc = a.wrapping_add(imm_i) & !1;
+                Some(link)
+            }
+            opcode::BRANCH => {
+                let taken = match funct3 {
+                    0b000 => Some(a == b),
+                    0b001 => Some(a != b),
+                    0b100 => Some((a as i32) < (b as i32)),
+                    0b101 => Some((a as i32) >= (b as i32)),
+                    0b110 => Some(a < b),
+                    0b111 => Some(a >= b),
+                    _ => None,
+                };
+                if taken == Some(true) {
+                    next_pc = self.pc.wrapping_add(imm_b);
+                }
+                None
+            }
+            opcode::LOAD => match funct3 {
+                0b000 | 0b001 | 0b010 | 0b100 | 0b101 => Some(self.load(a.wrapping_add(imm_i))),
+                _ => None,
+            },
+            opcode::STORE => {
+                if funct3 <= 0b010 {
+                    self.store(a.wrapping_add(imm_s), b);
+                }
+                None
+            }
+            opcode::OP_IMM => match funct3 {
+                0b001 if funct7 != 0 => None,
+                0b101 if funct7 & !0b010_0000 != 0 => None,
+                _ => Some(alu(funct3, funct3 == 0b101 && funct7 != 0, a, imm_i)),
+            },
+            opcode::OP => match funct7 {
+                0b000_0000 => Some(alu(funct3, false, a, b)),
+                0b010_0000 if funct3 == 0b000 || funct3 == 0b101 => Some(alu(funct3, true, a, b)),
+                _ => None,
+            },
+            opcode::MISC_MEM => None,
+            opcode::SYSTEM => match funct3 {
+                0b001..=0b011 => self.csr_instruction(instruction, a),
+                0b101..=0b111 => self.csr_instruction(instruction, rs1),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some(value) = result {
+            if rd != 0 {
+                self.registers[rd] = value;
+            }
+        }
+        self.pc = next_pc;
+        self.instret += 1;
+    }
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Model::new()
+    }
+}
+
+/* The software model runs in the calling thread.
+ */
+impl NarvieDevice for Model {
+    fn execute(&mut self, instruction: u32) -> Result<RegisterFile, DeviceError> {
+        Model::execute(self, instruction);
+        Ok(RegisterFile::new(self.registers()))
+    }
+
+    fn reset(&mut self) -> Result<bool, DeviceError> {
+        *self = Model::new();
+        Ok(true)
+    }
+}
+
+/* Runs the software model using the same protocol as `run_narvie`: each
+ * instruction is received as 4 little endian bytes and the register file is
+ * sent back as 128 little endian bytes. Returns when either channel is
+ * closed.
+ */
+pub fn run_model(sender: Sender<u8>, receiver: Receiver<u8>) {
+    let mut model = Model::new();
+
+    loop {
+        let mut bytes = [0; 4];
+        for byte in bytes.iter_mut() {
+            match receiver.recv() {
+                Ok(value) => *byte = value,
+                Err(_) => return,
+            }
+        }
+
+        model.execute(u32::from_le_bytes(bytes));
+
+        for register in model.registers().iter() {
+            for byte in register.to_le_bytes().iter() {
+                if sender.send(*byte).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+
+    fn run(instructions: &[u32]) -> Model {
+        let mut model = Model::new();
+        for instruction in instructions {
+            model.execute(*instruction);
+        }
+        model
+    }
+
+    #[test]
+    fn arithmetic() {
+        let model = run(&[
+            0xFFF0_0513, // addi a0, zero, -1
+            0x1234_52B7, // lui t0, 0x12345
+            0x0010_0013, // addi zero, zero, 1
+            0x4045_5693, // srai a3, a0, 4
+            0x0045_5713, // srli a4, a0, 4
+            0x40A2_87B3, // sub a5, t0, a0
+        ]);
+        let registers = model.registers();
+        assert_eq!(registers[0], 0);
+        assert_eq!(registers[10], 0xFFFF_FFFF);
+        assert_eq!(registers[5], 0x1234_5000);
+        assert_eq!(registers[13], 0xFFFF_FFFF);
+        assert_eq!(registers[14], 0x0FFF_FFFF);
+        assert_eq!(registers[15], 0x1234_5001);
+    }
+
+    #[test]
+    fn loads_and_stores() {
+        let registers = run(&[
+            0xFFF0_0513, // addi a0, zero, -1
+            0x00A0_2223, // sw a0, 4(zero)
+            0x0040_0583, // lb a1, 4(zero)
+            0x0050_2603, // lw a2, 5(zero)
+            0x1230_0693, // addi a3, zero, 0x123
+            0x00D0_02A3, // sb a3, 5(zero)
+            0x0050_4703, // lbu a4, 5(zero)
+            0x0040_2783, // lw a5, 4(zero)
+            0x00A0_2023, // sw a0, 0(zero)
+            0x8000_2803, // lw a6, -2048(zero)
+            0x7EA0_2FA3, // sw a0, 2047(zero)
+            0x7FF0_2883, // lw a7, 2047(zero)
+        ])
+        .registers();
+        assert_eq!(registers[11], 0xFFFF_FFFF);
+        assert_eq!(registers[12], 0);
+        assert_eq!(registers[14], 0x123);
+        assert_eq!(registers[15], 0xFFFF_FFFF);
+        assert_eq!(registers[16], 0);
+        assert_eq!(registers[17], 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn jumps_and_branches() {
+        let registers = run(&[
+            0x0080_00EF, // jal ra, 8
+            0x0000_0117, // auipc sp, 0
+            0x0000_1463, // bne zero, zero, 8
+            0x0000_0197, // auipc gp, 0
+            0x0000_0463, // beq zero, zero, 8
+            0x0000_0217, // auipc tp, 0
+        ])
+        .registers();
+        assert_eq!(registers[1], 4);
+        assert_eq!(registers[2], 8);
+        assert_eq!(registers[3], 16);
+        assert_eq!(registers[4], 28);
+    }
+
+    #[test]
+    fn csrs() {
+        let registers = run(&[
+            0x3402_D373, // csrrwi t1, mscratch, 5
+            0x3400_23F3, // csrrs t2, mscratch, zero
+            0xC020_22F3, // csrrs t0, instret, zero
+            0xC020_9E73, // csrrw t3, instret, ra
+            0xC020_2EF3, // csrrs t4, instret, zero
+        ])
+        .registers();
+        assert_eq!(registers[7], 5);
+        assert_eq!(registers[5], 2);
+        assert_eq!(registers[29], 4);
+    }
+
+    #[test]
+    fn byte_protocol() {
+        let (to_model, from_cli) = mpsc::channel();
+        let (to_cli, from_model) = mpsc::channel();
+        thread::spawn(move || run_model(to_cli, from_cli));
+
+        for byte in 0x02A0_0513u32.to_le_bytes().iter() {
+            to_model.send(*byte).unwrap();
+        }
+        let reply: Vec<u8> = (0..128).map(|_| from_model.recv().unwrap()).collect();
+        assert_eq!(reply[40..44], [42, 0, 0, 0]);
+        assert!(reply[..40]
+            .iter()
+            .chain(&reply[44..])
+            .all(|byte| *byte == 0));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::model;
+use narvie_isa::device::{DeviceError, NarvieDevice};
+use narvie_isa::register_file::RegisterFile;
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/* A simulation of narvie running on its own thread, which is stopped when
+ * the handle is dropped.
+ */
+pub struct Simulation {
+    run: fn(Sender<u8>, Receiver<u8>),
+    /* `None` once the simulation has been told to stop. */
+    to_simulation: Option<Sender<u8>>,
+    from_simulation: Receiver<u8>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Simulation {
+    /* Starts the Verilator simulation of the narvie verilog.
+     */
+    #[cfg(feature = "verilator")]
+    pub fn start() -> Self {
+        Simulation::start_with(crate::run_narvie)
+    }
+
+    /* Starts the software model, on its own thread like the Verilator
+     * simulation.
+     */
+    pub fn start_model() -> Self {
+        Simulation::start_with(model::run_model)
+    }
+
+    fn start_with(run: fn(Sender<u8>, Receiver<u8>)) -> Self {
+        let (to_simulation, receiver) = mpsc::channel();
+        let (sender, from_simulation) = mpsc::channel();
+        Simulation {
+            run,
+            to_simulation: Some(to_simulation),
+            from_simulation,
+            thread: Some(thread::spawn(move || run(sender, receiver))),
+        }
+    }
+
+    /* Replaces the simulation with a new one, so that every register is zero
+     * again.
+     */
+    pub fn reset(&mut self) {
+        self.shutdown();
+        *self = Simulation::start_with(self.run);
+    }
+
+    /* Waits for the simulation to finish any instruction it is evaluating
+     * and for its thread to exit.
+     */
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        /* The simulation returns once the channel it reads from is closed. */
+        self.to_simulation = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the simulation has stopped")
+}
+
+impl NarvieDevice for Simulation {
+    fn execute(&mut self, instruction: u32) -> Result<RegisterFile, DeviceError> {
+        let to_simulation = self
+            .to_simulation
+            .as_ref()
+            .ok_or_else(|| DeviceError::Write(stopped()))?;
+        for byte in instruction.to_le_bytes().iter() {
+            to_simulation
+                .send(*byte)
+                .map_err(|_| DeviceError::Write(stopped()))?;
+        }
+
+        let mut bytes = [0; RegisterFile::BYTES];
+        for byte in bytes.iter_mut() {
+            *byte = self
+                .from_simulation
+                .recv()
+                .map_err(|_| DeviceError::Read(stopped()))?;
+        }
+        Ok(RegisterFile::from_le_bytes(&bytes))
+    }
+
+    fn reset(&mut self) -> Result<bool, DeviceError> {
+        Simulation::reset(self);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_reset_and_stop() {
+        let mut simulation = Simulation::start_model();
+        assert_eq!(simulation.execute(0x0050_0513).unwrap()["a0"], 5); // addi a0, zero, 5
+        assert_eq!(simulation.execute(0x0000_0013).unwrap()["a0"], 5); // nop
+
+        simulation.reset();
+        assert_eq!(
+            simulation.execute(0x0000_0013).unwrap(),
+            RegisterFile::default()
+        );
+
+        simulation.stop();
+    }
+}
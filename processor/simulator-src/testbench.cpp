@@ -94,10 +94,10 @@ void main_loop(int (*write)(uint8_t, void *), int (*try_read)(uint8_t *, void *)
 	UartSimulator_init(&uart, write, try_read, read_write_state);
 	NarvieSimulator_init(&simulator, &core, &uart);
 
-	while (1)
+	// Run until there is nothing left to read, so that a simulation can be
+	// stopped without stopping the whole process.
+	while (!uart.closed)
 	{
 		NarvieSimulator_tick(&simulator);
 	}
-
-	exit(EXIT_SUCCESS);
 }
@@ -9,6 +9,7 @@ void UartSimulator_init(UartSimulator *simulator, int (*write)(uint8_t, void *),
         simulator->write = write;
         simulator->try_read = try_read;
         simulator->read_write_state = read_write_state;
+        simulator->closed = 0;
 
         // UART state
         simulator->rx_baudcounter = 0;
@@ -83,7 +84,12 @@ int UartSimulator_tick(UartSimulator *simulator, int i_tx)
         if (simulator->tx_state == TXIDLE)
         {
                 uint8_t buf;
-                if (simulator->try_read(&buf, simulator->read_write_state) == 0)
+                int result = simulator->try_read(&buf, simulator->read_write_state);
+                if (result < 0)
+                {
+                        simulator->closed = 1;
+                }
+                else if (result == 0)
                 {
                         simulator->tx_data = ((~0u) << (8 + 0 + 1)) | (buf << 1);
                         simulator->tx_busy = (1 << (8 + 0 + 1 + 1)) - 1;
@@ -27,6 +27,8 @@ extern "C"
 		/* negative number on error. */
 		int (*try_read)(uint8_t *, void *);
 		void *read_write_state;
+		/* set once try_read returns an error.			*/
+		int closed;
 
 		// UART state
 		int rx_baudcounter;